@@ -1,6 +1,3 @@
-mod common;
-
-use crate::common::graceperiod::{GracePeriodPlugin, GracefulFrameCount};
 use bevy::{
     camera::RenderTarget,
     prelude::*,
@@ -11,7 +8,9 @@ use bevy::{
         RenderPlugin,
     },
 };
-use bevy_image_export::{ImageExport, ImageExportPlugin, ImageExportSource};
+use bevy_image_export::{
+    ImageExport, ImageExportPlugin, ImageExportSource, ImageExportStart, ImageExportSystems,
+};
 use std::f32::consts::PI;
 
 const WIDTH: u32 = 768;
@@ -35,7 +34,6 @@ fn main() {
                     synchronous_pipeline_compilation: true,
                     ..default()
                 }),
-            GracePeriodPlugin::default(),
             export_plugin,
         ))
         .insert_resource(GlobalAmbientLight {
@@ -43,7 +41,13 @@ fn main() {
             brightness: 1000.0,
             affects_lightmapped_meshes: true,
         })
-        .add_systems(Update, (setup, update).chain())
+        .add_systems(Startup, setup)
+        .add_systems(
+            PostUpdate,
+            update
+                .after(ImageExportSystems::ImageExportSetup)
+                .before(TransformSystems::Propagate),
+        )
         .run();
 
     export_threads.finish();
@@ -55,12 +59,7 @@ fn setup(
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut images: ResMut<Assets<Image>>,
     mut export_sources: ResMut<Assets<ImageExportSource>>,
-    frame_count: Res<GracefulFrameCount>,
 ) {
-    if frame_count.frame() != 1 {
-        return;
-    }
-
     let output_texture_handle = {
         let size = Extent3d {
             width: WIDTH,
@@ -109,12 +108,16 @@ fn setup(
 #[derive(Component)]
 struct Moving;
 
+/// Moves the scene for the frame that is exported in the current update.
 fn update(
     mut transforms: Query<&mut Transform, With<Moving>>,
-    frame_count: Res<GracefulFrameCount>,
+    exporters: Query<&ImageExportStart>,
 ) {
-    let frame = frame_count.frame().wrapping_sub(1);
-    let theta = frame as f32 * 0.25 * PI;
+    let Ok(start) = exporters.single() else {
+        return;
+    };
+
+    let theta = (start.frame_id() - 1) as f32 * 0.25 * PI;
     for mut transform in &mut transforms {
         transform.translation = Vec3::new(theta.sin(), theta.cos(), 0.0);
     }
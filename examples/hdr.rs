@@ -1,6 +1,3 @@
-mod common;
-
-use crate::common::graceperiod::{GracePeriodPlugin, GracefulFrameCount};
 use bevy::{
     camera::RenderTarget,
     core_pipeline::tonemapping::Tonemapping,
@@ -13,7 +10,10 @@ use bevy::{
         RenderPlugin,
     },
 };
-use bevy_image_export::{ImageExport, ImageExportPlugin, ImageExportSettings, ImageExportSource};
+use bevy_image_export::{
+    ExportFormat, ImageExport, ImageExportPlugin, ImageExportSettings, ImageExportSource,
+    ImageExportStart, ImageExportSystems,
+};
 use std::f32::consts::PI;
 
 const WIDTH: u32 = 768;
@@ -37,11 +37,15 @@ fn main() {
                     synchronous_pipeline_compilation: true,
                     ..default()
                 }),
-            GracePeriodPlugin::default(),
             export_plugin,
         ))
-        .add_systems(Startup, setup_scene)
-        .add_systems(Update, (setup_camera, update).chain())
+        .add_systems(Startup, (setup_scene, setup_camera))
+        .add_systems(
+            PostUpdate,
+            update
+                .after(ImageExportSystems::ImageExportSetup)
+                .before(TransformSystems::Propagate),
+        )
         .run();
 
     export_threads.finish();
@@ -75,12 +79,7 @@ fn setup_camera(
     mut commands: Commands,
     mut images: ResMut<Assets<Image>>,
     mut exporter_sources: ResMut<Assets<ImageExportSource>>,
-    frame_count: Res<GracefulFrameCount>,
 ) {
-    if frame_count.frame() != 1 {
-        return;
-    }
-
     let output_texture_handle = {
         let size = Extent3d {
             width: WIDTH,
@@ -92,7 +91,7 @@ fn setup_camera(
                 label: None,
                 size,
                 dimension: TextureDimension::D2,
-                format: TextureFormat::Rgba32Float,
+                format: TextureFormat::Rgba16Float,
                 mip_level_count: 1,
                 sample_count: 1,
                 usage: TextureUsages::COPY_DST
@@ -129,7 +128,7 @@ fn setup_camera(
     commands.spawn((
         ImageExport(exporter_sources.add(output_texture_handle)),
         ImageExportSettings {
-            extension: "exr".into(),
+            format: ExportFormat::Exr,
             ..default()
         },
     ));
@@ -137,12 +136,16 @@ fn setup_camera(
 
 #[derive(Component)]
 struct Moving;
+/// Moves the scene for the frame that is exported in the current update.
 fn update(
     mut transforms: Query<&mut Transform, With<Moving>>,
-    frame_count: Res<GracefulFrameCount>,
+    exporters: Query<&ImageExportStart>,
 ) {
-    let frame = frame_count.frame().wrapping_sub(1);
-    let theta = frame as f32 * 0.25 * PI;
+    let Ok(start) = exporters.single() else {
+        return;
+    };
+
+    let theta = (start.frame_id() - 1) as f32 * 0.25 * PI;
     for mut transform in &mut transforms {
         transform.translation = 10.0 * Vec3::new(theta.sin(), theta.cos(), 0.5);
     }
@@ -0,0 +1,207 @@
+#[cfg(feature = "apng")]
+use crate::encoder::write_apng;
+#[cfg(feature = "gif")]
+use crate::encoder::write_gif;
+#[cfg(feature = "webp")]
+use crate::encoder::write_webp;
+use crate::{
+    storage::{decode_frame, encode_colors, into_rgba8, ImageStorageError},
+    AnimationFormat, BitDepth, EncoderSettings, ExportSink, ExportThreads, ExportedFrame,
+    ImageExportSettings, ImageExportStart, ImageExportState, TransferFunction,
+};
+use bevy::{
+    platform::collections::HashMap, prelude::*, render::extract_component::ExtractComponent,
+};
+use crossbeam_channel::Sender;
+use image::RgbaImage;
+use std::{fs::create_dir_all, path::PathBuf, sync::Arc};
+
+/// Makes an [`ImageExport`](crate::ImageExport) save its frames as a single animated file instead
+/// of an image sequence.
+///
+/// Frames are collected in memory until the export ends, i.e. until the exporter is
+/// [stopped](ImageExportState::Stopped), reaches the end of its
+/// [`ImageExportRange`](crate::ImageExportRange) or loses this component. The file is then written
+/// in the background, which [`ExportThreads::finish`] waits for. Recording again starts a new
+/// animation at the same path.
+///
+/// Frames are scaled down to the [`max_size`](ImageExportSettings::max_size) of the exporter and
+/// encoded with its [`transfer_function`](ImageExportSettings::transfer_function). Like with
+/// [`ImageExportPipe`](crate::ImageExportPipe)s, frames are skipped while the source has a
+/// different size than when the export started.
+///
+/// ```ignore
+/// commands.spawn((
+///     ImageExport(export_sources.add(output_texture_handle)),
+///     ImageExportAnimation::new("out/clip.gif", AnimationFormat::Gif, 30),
+///     ImageExportRange::new(1..=90),
+/// ));
+/// ```
+#[derive(Component, ExtractComponent, Clone, Debug)]
+pub struct ImageExportAnimation {
+    /// The path that the animation will be saved to.
+    pub path: PathBuf,
+    pub format: AnimationFormat,
+    /// The number of frames per second of the animation.
+    pub frame_rate: u32,
+    /// Plays the animation in an endless loop instead of once.
+    pub looping: bool,
+}
+
+impl ImageExportAnimation {
+    /// Saves an endlessly looping animation to `path`.
+    pub fn new(path: impl Into<PathBuf>, format: AnimationFormat, frame_rate: u32) -> Self {
+        Self {
+            path: path.into(),
+            format,
+            frame_rate,
+            looping: true,
+        }
+    }
+
+    pub fn looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+
+    #[cfg_attr(not(feature = "apng"), allow(unused_variables))]
+    fn save(
+        &self,
+        frames: Vec<RgbaImage>,
+        encoder: &EncoderSettings,
+        transfer_function: Option<TransferFunction>,
+    ) -> Result<(), ImageStorageError> {
+        if let Some(parent) = self.path.parent() {
+            create_dir_all(parent)?;
+        }
+
+        match self.format {
+            #[cfg(feature = "gif")]
+            AnimationFormat::Gif => write_gif(
+                &self.path,
+                frames,
+                self.frame_rate,
+                self.looping,
+                &encoder.gif,
+            ),
+            #[cfg(feature = "apng")]
+            AnimationFormat::Apng => write_apng(
+                &self.path,
+                frames,
+                self.frame_rate,
+                self.looping,
+                &encoder.png,
+                transfer_function,
+            ),
+            #[cfg(feature = "webp")]
+            AnimationFormat::WebP => write_webp(&self.path, frames, self.frame_rate, self.looping),
+            #[allow(unreachable_patterns)]
+            format => Err(ImageStorageError::AnimationFormatDisabled(format)),
+        }
+    }
+}
+
+/// Handle to an animation that is being recorded, collected by a dedicated thread.
+pub(crate) struct AnimationWriter {
+    sender: Sender<ExportedFrame>,
+}
+
+impl AnimationWriter {
+    fn spawn(
+        animation: &ImageExportAnimation,
+        settings: &ImageExportSettings,
+        export_threads: &ExportThreads,
+    ) -> Self {
+        let (sender, receiver) = export_threads.bounded::<ExportedFrame>();
+        let animation = animation.clone();
+        let encoder = settings.encoder.clone();
+        let max_size = settings.max_size;
+        let transfer_function = settings.transfer_function;
+
+        export_threads.spawn(move || {
+            // Frames are decoded as they arrive, so that only their 8-bit versions are kept.
+            let mut frames = Vec::new();
+            for frame in receiver {
+                let texture_format = frame.format;
+                let image = match decode_frame(frame, max_size) {
+                    Ok(image) => image,
+                    Err(err) => {
+                        error!({ error = %err }, "failed decoding animation frame");
+                        continue;
+                    }
+                };
+
+                frames.push(into_rgba8(match transfer_function {
+                    Some(transfer_function) => encode_colors(
+                        image,
+                        texture_format,
+                        transfer_function,
+                        Some(BitDepth::Eight),
+                    ),
+                    None => image,
+                }));
+            }
+
+            if frames.is_empty() {
+                return;
+            }
+
+            if let Err(err) = animation.save(frames, &encoder, transfer_function) {
+                error!(
+                    { error = %err, path = %animation.path.display() },
+                    "failed saving animation to disk"
+                );
+            }
+        });
+
+        Self { sender }
+    }
+}
+
+impl ExportSink for AnimationWriter {
+    fn write_frame(&self, frame: ExportedFrame, export_threads: &ExportThreads) {
+        // The animation is saved once the last handle to the writer is dropped.
+        export_threads.send(&self.sender, frame);
+    }
+}
+
+/// The animations of all recording exporters in the render world.
+///
+/// An animation is started when the first frame of its exporter is ready and saved once the export
+/// ends.
+#[derive(Resource, Default)]
+pub(crate) struct AnimationWriters(HashMap<Entity, Arc<AnimationWriter>>);
+
+impl AnimationWriters {
+    /// Returns the writer of `entity`, starting a new animation if necessary.
+    pub(crate) fn get_or_spawn(
+        &mut self,
+        entity: Entity,
+        animation: &ImageExportAnimation,
+        settings: &ImageExportSettings,
+        export_threads: &ExportThreads,
+    ) -> Arc<AnimationWriter> {
+        self.0
+            .entry(entity)
+            .or_insert_with(|| {
+                Arc::new(AnimationWriter::spawn(animation, settings, export_threads))
+            })
+            .clone()
+    }
+}
+
+/// Ends the animations of exporters that have been stopped or lost their
+/// [`ImageExportAnimation`].
+///
+/// Frames that are still being read back keep their writer alive, so the animation is saved after
+/// its last frame.
+pub(crate) fn close_animations(
+    mut writers: ResMut<AnimationWriters>,
+    animations: Query<&ImageExportState, (With<ImageExportAnimation>, With<ImageExportStart>)>,
+) {
+    writers.0.retain(|entity, _| {
+        animations
+            .get(*entity)
+            .is_ok_and(|state| *state != ImageExportState::Stopped)
+    });
+}
@@ -0,0 +1,146 @@
+use crate::{
+    storage::save_image, EncoderSettings, ExportFormat, ExportSink, ExportThreads, ExportedFrame,
+    TransferFunction,
+};
+use bevy::{ecs::entity::EntityHashMap, prelude::*, render::extract_component::ExtractComponent};
+use crossbeam_channel::{Receiver, Sender};
+use std::{
+    path::PathBuf,
+    sync::atomic::{AtomicU64, Ordering},
+};
+
+/// The ID of the next capture request. IDs are never reused, so that a capture that replaces a
+/// taken one, even in a new component, isn't mistaken for it.
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(1);
+
+/// Makes an [`ImageExport`](crate::ImageExport) save single frames on demand instead of a
+/// continuous sequence.
+///
+/// The exporter stays idle until a capture is requested. The next frame that it exports is then
+/// saved to the requested path in the [`format`](crate::ImageExportSettings::format) of its
+/// [`ImageExportSettings`](crate::ImageExportSettings), and the exporter goes idle again. A capture
+/// stays pending while frames are skipped, e.g. while the exporter is paused.
+///
+/// ```ignore
+/// commands.spawn((
+///     ImageExport(export_sources.add(output_texture_handle)),
+///     ImageExportCapture::new("out/thumbnail.png"),
+/// ));
+/// ```
+#[derive(Component, ExtractComponent, Clone, Default, Debug)]
+pub struct ImageExportCapture {
+    path: Option<PathBuf>,
+    /// Identifies the requested capture, so that taking one doesn't clear a newer request.
+    request: u64,
+}
+
+impl ImageExportCapture {
+    /// Captures the next rendered frame to `path`.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: Some(path.into()),
+            request: NEXT_REQUEST.fetch_add(1, Ordering::Relaxed),
+        }
+    }
+
+    /// Captures the next rendered frame to `path`, replacing any capture that hasn't been taken
+    /// yet.
+    pub fn capture(&mut self, path: impl Into<PathBuf>) {
+        self.path = Some(path.into());
+        self.request = NEXT_REQUEST.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Returns the path of the requested capture, if it hasn't been taken yet.
+    pub fn pending(&self) -> Option<&PathBuf> {
+        self.path.as_ref()
+    }
+}
+
+/// Reports the captures that have been queued in the render world.
+#[derive(Resource)]
+pub(crate) struct TakenCaptureReceiver(pub(crate) Receiver<(Entity, u64)>);
+
+/// The captures that have been queued in the render world.
+///
+/// A capture stays pending in the main world until it has been reported as taken, so it may be
+/// extracted again in the meantime. It is only taken once.
+///
+/// Reports are only sent to clear the main world's captures, so a send error, which means that the
+/// main world has been dropped, is ignored.
+#[derive(Resource)]
+pub(crate) struct TakenCaptures {
+    sender: Sender<(Entity, u64)>,
+    requests: EntityHashMap<u64>,
+}
+
+impl TakenCaptures {
+    pub(crate) fn new(sender: Sender<(Entity, u64)>) -> Self {
+        Self {
+            sender,
+            requests: EntityHashMap::default(),
+        }
+    }
+
+    /// Returns the path of the pending capture of `entity` unless it has been taken already,
+    /// marking it as taken.
+    ///
+    /// Call this only once the frame is sure to be queued.
+    pub(crate) fn take(
+        &mut self,
+        entity: Entity,
+        main_entity: Entity,
+        capture: &ImageExportCapture,
+    ) -> Option<PathBuf> {
+        let Some(path) = &capture.path else {
+            self.requests.remove(&entity);
+            return None;
+        };
+
+        if self.requests.insert(entity, capture.request) == Some(capture.request) {
+            return None;
+        }
+
+        let _ = self.sender.send((main_entity, capture.request));
+        Some(path.clone())
+    }
+}
+
+/// Clears the captures that have been taken, unless another one has been requested since.
+pub(crate) fn clear_captures(
+    receiver: Res<TakenCaptureReceiver>,
+    mut captures: Query<&mut ImageExportCapture>,
+) {
+    for (entity, request) in receiver.0.try_iter() {
+        if let Ok(mut capture) = captures.get_mut(entity) {
+            if capture.request == request {
+                capture.path = None;
+            }
+        }
+    }
+}
+
+/// Saves a single frame to a fixed path.
+pub(crate) struct CaptureSink {
+    pub(crate) path: PathBuf,
+    pub(crate) format: ExportFormat,
+    pub(crate) encoder: EncoderSettings,
+    pub(crate) max_size: Option<UVec2>,
+    pub(crate) transfer_function: Option<TransferFunction>,
+}
+
+impl ExportSink for CaptureSink {
+    fn write_frame(&self, frame: ExportedFrame, export_threads: &ExportThreads) {
+        let path = self.path.clone();
+        let format = self.format;
+        let encoder = self.encoder.clone();
+        let max_size = self.max_size;
+        let transfer_function = self.transfer_function;
+        export_threads.execute(move || {
+            if let Err(err) =
+                save_image(&path, format, &encoder, max_size, transfer_function, frame)
+            {
+                error!({ error = %err }, "failed saving capture to disk");
+            }
+        });
+    }
+}
@@ -0,0 +1,105 @@
+/// The transfer function that the color values of saved files are encoded with.
+///
+/// Frames are converted on the saving threads. Their values are decoded to linear light first,
+/// using the sRGB transfer function for sRGB textures, and then encoded with the chosen function.
+/// Colors keep the primaries of the render, i.e. those of Rec. 709 and sRGB, and alpha stays
+/// linear.
+///
+/// PNG files are marked with the transfer function in their `cICP` chunk, along with the `sRGB`
+/// or `gAMA` chunk where these can describe it. Other formats don't store it, and EXR and HDR
+/// readers usually expect linear values.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TransferFunction {
+    Linear,
+    Srgb,
+    /// The Rec. 709 OETF of HDTV video.
+    Rec709,
+    /// The perceptual quantizer of Rec. 2100, which maps a linear value of 1 to the reference
+    /// white of 203 cd/m² recommended by Rec. 2408.
+    Pq,
+    /// The hybrid log-gamma curve of Rec. 2100, which maps a linear value of 1 to the reference
+    /// white at 75% of the signal range.
+    Hlg,
+}
+
+/// The luminance in cd/m² that the PQ curve maps a linear value of 1 to.
+const PQ_REFERENCE_WHITE: f32 = 203.0;
+
+/// The linear scene light that the HLG OETF maps to 75% of the signal range.
+const HLG_REFERENCE_WHITE: f32 = 0.2647;
+
+impl TransferFunction {
+    /// Encodes a linear color value, clamping negative values to zero.
+    pub(crate) fn encode(self, value: f32) -> f32 {
+        let value = value.max(0.0);
+        match self {
+            Self::Linear => value,
+            Self::Srgb => linear_to_srgb(value),
+            Self::Rec709 => {
+                if value < 0.018 {
+                    4.5 * value
+                } else {
+                    1.099 * value.powf(0.45) - 0.099
+                }
+            }
+            Self::Pq => {
+                const M1: f32 = 2610.0 / 16384.0;
+                const M2: f32 = 2523.0 / 4096.0 * 128.0;
+                const C1: f32 = 3424.0 / 4096.0;
+                const C2: f32 = 2413.0 / 4096.0 * 32.0;
+                const C3: f32 = 2392.0 / 4096.0 * 32.0;
+
+                let y = (value * PQ_REFERENCE_WHITE / 10000.0).min(1.0).powf(M1);
+                ((C1 + C2 * y) / (1.0 + C3 * y)).powf(M2)
+            }
+            Self::Hlg => {
+                const A: f32 = 0.17883277;
+                const B: f32 = 0.28466892;
+                const C: f32 = 0.5599107;
+
+                let e = (value * HLG_REFERENCE_WHITE).min(1.0);
+                if e <= 1.0 / 12.0 {
+                    (3.0 * e).sqrt()
+                } else {
+                    A * (12.0 * e - B).ln() + C
+                }
+            }
+        }
+    }
+
+    /// The code of the transfer characteristics in ITU-T H.273, which `cICP` chunks refer to.
+    #[cfg(any(feature = "png", feature = "apng"))]
+    pub(crate) fn cicp_code(self) -> u8 {
+        match self {
+            Self::Linear => 8,
+            Self::Srgb => 13,
+            Self::Rec709 => 1,
+            Self::Pq => 16,
+            Self::Hlg => 18,
+        }
+    }
+}
+
+/// The CIE xy chromaticities of the red, green and blue primaries and the white point of Rec. 709
+/// and sRGB.
+#[cfg(any(feature = "png", feature = "apng", feature = "exr"))]
+pub(crate) const REC709_CHROMATICITIES: [(f32, f32); 4] =
+    [(0.64, 0.33), (0.30, 0.60), (0.15, 0.06), (0.3127, 0.3290)];
+
+/// Converts a linear color value to the sRGB transfer function.
+pub(crate) fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Converts a color value with the sRGB transfer function to linear light.
+pub(crate) fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
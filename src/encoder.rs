@@ -0,0 +1,776 @@
+#[cfg(any(feature = "png", feature = "apng", feature = "exr"))]
+use crate::color::REC709_CHROMATICITIES;
+#[cfg(any(
+    feature = "png",
+    feature = "jpeg",
+    feature = "exr",
+    feature = "tiff",
+    feature = "qoi",
+    feature = "bmp",
+    feature = "tga",
+    feature = "pnm",
+    feature = "hdr",
+    feature = "gif",
+    feature = "apng",
+    feature = "webp"
+))]
+use crate::storage::ImageStorageError;
+#[cfg(any(feature = "png", feature = "apng"))]
+use crate::TransferFunction;
+#[cfg(any(
+    feature = "png",
+    feature = "jpeg",
+    feature = "exr",
+    feature = "tiff",
+    feature = "qoi",
+    feature = "bmp",
+    feature = "tga",
+    feature = "pnm",
+    feature = "hdr",
+    feature = "gif",
+    feature = "apng",
+    feature = "webp"
+))]
+use std::path::Path;
+#[cfg(any(
+    feature = "png",
+    feature = "jpeg",
+    feature = "tiff",
+    feature = "qoi",
+    feature = "bmp",
+    feature = "tga",
+    feature = "pnm",
+    feature = "hdr",
+    feature = "gif",
+    feature = "apng",
+    feature = "webp"
+))]
+use std::{fs::File, io::BufWriter};
+
+/// The file format that frames are saved in.
+///
+/// Each format requires the cargo feature of the same name. Exporters whose format is disabled,
+/// or can't represent their source texture, are rejected with an error when they are spawned.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ExportFormat {
+    #[default]
+    Png,
+    Jpeg,
+    /// OpenEXR, which keeps the full range of HDR textures.
+    Exr,
+    /// TIFF, which keeps the 16-bit and float channels of the source texture.
+    Tiff,
+    /// The Quite OK Image format, which is lossless and much faster to encode than PNG.
+    Qoi,
+    Bmp,
+    Tga,
+    /// Binary PPM, or PGM for single-channel textures.
+    Pnm,
+    /// Radiance HDR, which keeps the full range of HDR textures but drops their alpha channel.
+    Hdr,
+}
+
+impl ExportFormat {
+    /// The file extension, which fills the `{ext}` placeholder of
+    /// [`ImageExportSettings::file_name`](crate::ImageExportSettings::file_name).
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpg",
+            Self::Exr => "exr",
+            Self::Tiff => "tiff",
+            Self::Qoi => "qoi",
+            Self::Bmp => "bmp",
+            Self::Tga => "tga",
+            Self::Pnm => "pnm",
+            Self::Hdr => "hdr",
+        }
+    }
+
+    /// The cargo feature that enables this format.
+    pub fn feature(self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Jpeg => "jpeg",
+            Self::Exr => "exr",
+            Self::Tiff => "tiff",
+            Self::Qoi => "qoi",
+            Self::Bmp => "bmp",
+            Self::Tga => "tga",
+            Self::Pnm => "pnm",
+            Self::Hdr => "hdr",
+        }
+    }
+
+    /// Checks if the cargo feature of this format is enabled.
+    pub fn is_enabled(self) -> bool {
+        match self {
+            Self::Png => cfg!(feature = "png"),
+            Self::Jpeg => cfg!(feature = "jpeg"),
+            Self::Exr => cfg!(feature = "exr"),
+            Self::Tiff => cfg!(feature = "tiff"),
+            Self::Qoi => cfg!(feature = "qoi"),
+            Self::Bmp => cfg!(feature = "bmp"),
+            Self::Tga => cfg!(feature = "tga"),
+            Self::Pnm => cfg!(feature = "pnm"),
+            Self::Hdr => cfg!(feature = "hdr"),
+        }
+    }
+
+    /// Checks if the format stores values outside `[0, 1]`.
+    pub fn is_hdr(self) -> bool {
+        matches!(self, Self::Exr | Self::Tiff | Self::Hdr)
+    }
+}
+
+/// The file format of an [`ImageExportAnimation`](crate::ImageExportAnimation).
+///
+/// Each format requires the cargo feature of the same name. Frames are stored with 8 bits per
+/// channel.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum AnimationFormat {
+    /// GIF, whose frames are quantized to a palette of 256 colors each.
+    #[default]
+    Gif,
+    /// Animated PNG, encoded with the [`compression`](PngSettings::compression) and
+    /// [`filter`](PngSettings::filter) of the exporter.
+    Apng,
+    /// Animated WebP with lossless frames.
+    WebP,
+}
+
+impl AnimationFormat {
+    /// The file extension.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "png",
+            Self::WebP => "webp",
+        }
+    }
+
+    /// The cargo feature that enables this format.
+    pub fn feature(self) -> &'static str {
+        match self {
+            Self::Gif => "gif",
+            Self::Apng => "apng",
+            Self::WebP => "webp",
+        }
+    }
+
+    /// Checks if the cargo feature of this format is enabled.
+    pub fn is_enabled(self) -> bool {
+        match self {
+            Self::Gif => cfg!(feature = "gif"),
+            Self::Apng => cfg!(feature = "apng"),
+            Self::WebP => cfg!(feature = "webp"),
+        }
+    }
+}
+
+/// Options for the image encoders, grouped by file format.
+#[derive(Clone, Debug, Default)]
+pub struct EncoderSettings {
+    pub png: PngSettings,
+    pub jpeg: JpegSettings,
+    pub exr: ExrSettings,
+    pub tiff: TiffSettings,
+    pub gif: GifSettings,
+}
+
+impl EncoderSettings {
+    /// Checks if files in the given format keep values outside `[0, 1]` with these settings.
+    pub(crate) fn keeps_hdr(&self, format: ExportFormat) -> bool {
+        format.is_hdr() && !(format == ExportFormat::Tiff && self.tiff.bit_depth.is_some())
+    }
+
+    /// Returns the number of bits per channel that files in the given format are quantized to with
+    /// these settings, or `None` if they keep the precision of the frames.
+    pub(crate) fn bit_depth(&self, format: ExportFormat) -> Option<BitDepth> {
+        match format {
+            ExportFormat::Png => Some(self.png.bit_depth),
+            ExportFormat::Tiff => self.tiff.bit_depth,
+            ExportFormat::Exr | ExportFormat::Hdr => None,
+            _ => Some(BitDepth::Eight),
+        }
+    }
+}
+
+/// The number of bits per channel of PNG and TIFF files.
+///
+/// Textures with less precision are expanded, e.g. 8-bit channels to 16 bits. Float textures are
+/// assumed to hold linear color values, which are clamped to `[0, 1]` and encoded with the sRGB
+/// transfer function, like when they are saved with 8 bits. Other textures keep the encoding of
+/// their values, e.g. `Rgba16Unorm` textures are saved as they are. A
+/// [`transfer_function`](crate::ImageExportSettings::transfer_function) replaces both.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum BitDepth {
+    #[default]
+    Eight,
+    Sixteen,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PngSettings {
+    pub compression: PngCompression,
+    pub filter: PngFilter,
+    /// Animated PNGs are always saved with 8 bits per channel.
+    pub bit_depth: BitDepth,
+}
+
+/// The DEFLATE compression level of PNG files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PngCompression {
+    Uncompressed,
+    #[default]
+    Fast,
+    Balanced,
+    Best,
+}
+
+/// The filter applied to each row of PNG files before compression.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PngFilter {
+    NoFilter,
+    Sub,
+    Up,
+    Avg,
+    Paeth,
+    /// Chooses a filter for each row.
+    #[default]
+    Adaptive,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct JpegSettings {
+    /// The quality from 1 to 100.
+    pub quality: u8,
+}
+
+impl Default for JpegSettings {
+    fn default() -> Self {
+        Self { quality: 75 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ExrSettings {
+    pub compression: ExrCompression,
+    pub precision: ExrPrecision,
+    /// Saves the [`ImageExportPasses`](crate::ImageExportPasses) of an exporter as layers of its
+    /// files instead of separate files.
+    ///
+    /// The channels of a pass are prefixed with its name, e.g. `depth.R`, while the channels of the
+    /// exporter keep their plain names. Every pass must have the size of the exporter's frames.
+    /// Frames are skipped while the source of a pass has nothing to copy, so that no file is
+    /// missing a layer.
+    pub combine_passes: bool,
+}
+
+/// The lossless compression method of EXR files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExrCompression {
+    Uncompressed,
+    /// Run-length encoding. Almost as fast as uncompressed data, but only shrinks areas of
+    /// uniform color.
+    #[default]
+    Rle,
+    /// Slow, but produces small files.
+    Zip,
+    /// Wavelet compression, which works well for images with film grain.
+    Piz,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TiffSettings {
+    /// Converts the channels of frames to this bit depth. If `None`, frames keep the precision of
+    /// their texture, i.e. 8 bits, 16 bits or 32-bit floats.
+    pub bit_depth: Option<BitDepth>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct GifSettings {
+    /// The speed of the palette quantization from 1 to 30. Lower values pick better palettes, but
+    /// take longer.
+    pub speed: u8,
+}
+
+impl Default for GifSettings {
+    fn default() -> Self {
+        Self { speed: 10 }
+    }
+}
+
+/// The sample type of EXR files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ExrPrecision {
+    /// 16-bit floats, which halve the file size.
+    Half,
+    /// 32-bit floats.
+    #[default]
+    Full,
+}
+
+/// Creates a PNG encoder for RGBA images with the given settings.
+///
+/// If a transfer function is given, the image is marked with the Rec. 709 primaries and the `sRGB`
+/// or `gAMA` chunk where these can describe it. The matching `cICP` chunk is written by
+/// [`write_cicp`].
+#[cfg(any(feature = "png", feature = "apng"))]
+fn png_encoder<W: std::io::Write>(
+    writer: W,
+    width: u32,
+    height: u32,
+    bit_depth: png::BitDepth,
+    settings: &PngSettings,
+    transfer_function: Option<TransferFunction>,
+) -> png::Encoder<'static, W> {
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(bit_depth);
+    encoder.set_compression(match settings.compression {
+        PngCompression::Uncompressed => png::Compression::NoCompression,
+        PngCompression::Fast => png::Compression::Fast,
+        PngCompression::Balanced => png::Compression::Balanced,
+        PngCompression::Best => png::Compression::High,
+    });
+    encoder.set_filter(match settings.filter {
+        PngFilter::NoFilter => png::Filter::NoFilter,
+        PngFilter::Sub => png::Filter::Sub,
+        PngFilter::Up => png::Filter::Up,
+        PngFilter::Avg => png::Filter::Avg,
+        PngFilter::Paeth => png::Filter::Paeth,
+        PngFilter::Adaptive => png::Filter::Adaptive,
+    });
+
+    let Some(transfer_function) = transfer_function else {
+        return encoder;
+    };
+
+    let [red, green, blue, white] = REC709_CHROMATICITIES;
+    encoder.set_source_chromaticities(png::SourceChromaticities::new(white, red, green, blue));
+    match transfer_function {
+        TransferFunction::Srgb => {
+            // Also written as `gAMA` for readers that don't know the `sRGB` chunk.
+            encoder.set_source_gamma(png::ScaledFloat::from_scaled(45455));
+            encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        }
+        TransferFunction::Linear => encoder.set_source_gamma(png::ScaledFloat::new(1.0)),
+        _ => {}
+    }
+
+    encoder
+}
+
+/// Writes the `cICP` chunk that describes the colorimetry of a PNG image.
+#[cfg(any(feature = "png", feature = "apng"))]
+fn write_cicp<W: std::io::Write>(
+    writer: &mut png::Writer<W>,
+    transfer_function: Option<TransferFunction>,
+) -> Result<(), ImageStorageError> {
+    if let Some(transfer_function) = transfer_function {
+        // Rec. 709 primaries, RGB samples in the full range.
+        writer.write_chunk(png::chunk::cICP, &[1, transfer_function.cicp_code(), 0, 1])?;
+    }
+
+    Ok(())
+}
+
+/// Saves an 8-bit or 16-bit RGBA image as PNG.
+#[cfg(feature = "png")]
+pub(crate) fn write_png(
+    path: &Path,
+    image: &image::DynamicImage,
+    settings: &PngSettings,
+    transfer_function: Option<TransferFunction>,
+) -> Result<(), ImageStorageError> {
+    use image::DynamicImage;
+    use std::borrow::Cow;
+
+    // PNG stores 16-bit samples in big-endian order.
+    let (bit_depth, data) = match image {
+        DynamicImage::ImageRgba16(image) => (
+            png::BitDepth::Sixteen,
+            Cow::Owned(image.iter().flat_map(|value| value.to_be_bytes()).collect()),
+        ),
+        DynamicImage::ImageRgba8(image) => (png::BitDepth::Eight, Cow::Borrowed(image.as_raw())),
+        image => (
+            png::BitDepth::Eight,
+            Cow::Owned(image.to_rgba8().into_raw()),
+        ),
+    };
+
+    let mut writer = png_encoder(
+        BufWriter::new(File::create(path)?),
+        image.width(),
+        image.height(),
+        bit_depth,
+        settings,
+        transfer_function,
+    )
+    .write_header()?;
+    write_cicp(&mut writer, transfer_function)?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+
+    Ok(())
+}
+
+#[cfg(feature = "jpeg")]
+pub(crate) fn write_jpeg(
+    path: &Path,
+    image: &image::RgbImage,
+    settings: &JpegSettings,
+) -> Result<(), ImageStorageError> {
+    use image::codecs::jpeg::JpegEncoder;
+
+    JpegEncoder::new_with_quality(
+        BufWriter::new(File::create(path)?),
+        settings.quality.clamp(1, 100),
+    )
+    .encode_image(image)?;
+
+    Ok(())
+}
+
+#[cfg(feature = "exr")]
+pub(crate) fn write_exr(
+    path: &Path,
+    image: &image::Rgba32FImage,
+    settings: &ExrSettings,
+) -> Result<(), ImageStorageError> {
+    use exr::prelude::{f16, Image, Layer, LayerAttributes, SpecificChannels, Vec2, WritableImage};
+
+    let encoding = exr_encoding(settings.compression);
+    let size = (image.width() as usize, image.height() as usize);
+    let pixel = |Vec2(x, y): Vec2<usize>| image.get_pixel(x as u32, y as u32).0;
+
+    match settings.precision {
+        ExrPrecision::Half => Image::new(
+            exr_attributes(size),
+            Layer::new(
+                size,
+                LayerAttributes::default(),
+                encoding,
+                SpecificChannels::rgba(|position| {
+                    let [r, g, b, a] = pixel(position).map(f16::from_f32);
+                    (r, g, b, a)
+                }),
+            ),
+        )
+        .write()
+        .to_file(path)?,
+        ExrPrecision::Full => Image::new(
+            exr_attributes(size),
+            Layer::new(
+                size,
+                LayerAttributes::default(),
+                encoding,
+                SpecificChannels::rgba(|position| {
+                    let [r, g, b, a] = pixel(position);
+                    (r, g, b, a)
+                }),
+            ),
+        )
+        .write()
+        .to_file(path)?,
+    }
+
+    Ok(())
+}
+
+/// Saves several images of the same size as the layers of a single EXR file.
+///
+/// The channels of a layer are prefixed with its name, if it has one.
+#[cfg(feature = "exr")]
+pub(crate) fn write_exr_layers(
+    path: &Path,
+    layers: &[(Option<&str>, image::Rgba32FImage)],
+    settings: &ExrSettings,
+) -> Result<(), ImageStorageError> {
+    use exr::prelude::{
+        f16, AnyChannel, AnyChannels, FlatSamples, Image, Layer, LayerAttributes, WritableImage,
+    };
+
+    let Some((_, first)) = layers.first() else {
+        return Ok(());
+    };
+
+    let mut channels = Vec::with_capacity(4 * layers.len());
+    for (name, image) in layers {
+        for (index, channel) in ["R", "G", "B", "A"].into_iter().enumerate() {
+            let samples = image.pixels().map(|pixel| pixel.0[index]);
+            let samples = match settings.precision {
+                ExrPrecision::Half => FlatSamples::F16(samples.map(f16::from_f32).collect()),
+                ExrPrecision::Full => FlatSamples::F32(samples.collect()),
+            };
+            let name = name.map_or_else(|| channel.to_owned(), |name| format!("{name}.{channel}"));
+
+            channels.push(AnyChannel::new(name.as_str(), samples));
+        }
+    }
+
+    let size = (first.width() as usize, first.height() as usize);
+    Image::new(
+        exr_attributes(size),
+        Layer::new(
+            size,
+            LayerAttributes::default(),
+            exr_encoding(settings.compression),
+            AnyChannels::sort(channels.into()),
+        ),
+    )
+    .write()
+    .to_file(path)?;
+
+    Ok(())
+}
+
+/// The attributes of an EXR image of the given size, which mark its colors with the Rec. 709
+/// primaries.
+#[cfg(feature = "exr")]
+fn exr_attributes(size: (usize, usize)) -> exr::meta::header::ImageAttributes {
+    use exr::{
+        meta::{attribute::Chromaticities, header::ImageAttributes},
+        prelude::Vec2,
+    };
+
+    let [red, green, blue, white] = REC709_CHROMATICITIES.map(|(x, y)| Vec2(x, y));
+    ImageAttributes {
+        chromaticities: Some(Chromaticities {
+            red,
+            green,
+            blue,
+            white,
+        }),
+        ..ImageAttributes::with_size(size)
+    }
+}
+
+#[cfg(feature = "exr")]
+fn exr_encoding(compression: ExrCompression) -> exr::prelude::Encoding {
+    use exr::prelude::Encoding;
+
+    match compression {
+        ExrCompression::Uncompressed => Encoding::UNCOMPRESSED,
+        ExrCompression::Rle => Encoding::FAST_LOSSLESS,
+        ExrCompression::Zip => Encoding::SMALL_LOSSLESS,
+        ExrCompression::Piz => Encoding::SMALL_FAST_LOSSLESS,
+    }
+}
+
+#[cfg(feature = "tiff")]
+pub(crate) fn write_tiff(
+    path: &Path,
+    image: &image::DynamicImage,
+) -> Result<(), ImageStorageError> {
+    use image::codecs::tiff::TiffEncoder;
+
+    image.write_with_encoder(TiffEncoder::new(BufWriter::new(File::create(path)?)))?;
+
+    Ok(())
+}
+
+#[cfg(feature = "qoi")]
+pub(crate) fn write_qoi(path: &Path, image: &image::RgbaImage) -> Result<(), ImageStorageError> {
+    use image::codecs::qoi::QoiEncoder;
+
+    image.write_with_encoder(QoiEncoder::new(BufWriter::new(File::create(path)?)))?;
+
+    Ok(())
+}
+
+#[cfg(feature = "bmp")]
+pub(crate) fn write_bmp(path: &Path, image: &image::DynamicImage) -> Result<(), ImageStorageError> {
+    use image::codecs::bmp::BmpEncoder;
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    image.write_with_encoder(BmpEncoder::new(&mut writer))?;
+
+    Ok(())
+}
+
+#[cfg(feature = "tga")]
+pub(crate) fn write_tga(path: &Path, image: &image::DynamicImage) -> Result<(), ImageStorageError> {
+    use image::codecs::tga::TgaEncoder;
+
+    image.write_with_encoder(TgaEncoder::new(BufWriter::new(File::create(path)?)))?;
+
+    Ok(())
+}
+
+/// Saves an RGB image as PPM, or a grayscale image as PGM.
+#[cfg(feature = "pnm")]
+pub(crate) fn write_pnm(path: &Path, image: &image::DynamicImage) -> Result<(), ImageStorageError> {
+    use image::codecs::pnm::{PnmEncoder, PnmSubtype, SampleEncoding};
+
+    let subtype = if image.color().has_color() {
+        PnmSubtype::Pixmap(SampleEncoding::Binary)
+    } else {
+        PnmSubtype::Graymap(SampleEncoding::Binary)
+    };
+
+    image.write_with_encoder(
+        PnmEncoder::new(BufWriter::new(File::create(path)?)).with_subtype(subtype),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(feature = "hdr")]
+pub(crate) fn write_hdr(path: &Path, image: &image::Rgb32FImage) -> Result<(), ImageStorageError> {
+    use image::codecs::hdr::HdrEncoder;
+
+    image.write_with_encoder(HdrEncoder::new(BufWriter::new(File::create(path)?)))?;
+
+    Ok(())
+}
+
+#[cfg(feature = "gif")]
+pub(crate) fn write_gif(
+    path: &Path,
+    frames: Vec<image::RgbaImage>,
+    frame_rate: u32,
+    looping: bool,
+    settings: &GifSettings,
+) -> Result<(), ImageStorageError> {
+    use image::{
+        codecs::gif::{GifEncoder, Repeat},
+        Delay, Frame,
+    };
+
+    let mut encoder = GifEncoder::new_with_speed(
+        BufWriter::new(File::create(path)?),
+        settings.speed.clamp(1, 30) as i32,
+    );
+    encoder.set_repeat(if looping {
+        Repeat::Infinite
+    } else {
+        Repeat::Finite(0)
+    })?;
+
+    // GIF delays have a resolution of 10 ms.
+    let delay = Delay::from_numer_denom_ms(1000, frame_rate.max(1));
+    encoder.encode_frames(
+        frames
+            .into_iter()
+            .map(|frame| Frame::from_parts(frame, 0, 0, delay)),
+    )?;
+
+    Ok(())
+}
+
+#[cfg(feature = "apng")]
+pub(crate) fn write_apng(
+    path: &Path,
+    frames: Vec<image::RgbaImage>,
+    frame_rate: u32,
+    looping: bool,
+    settings: &PngSettings,
+    transfer_function: Option<TransferFunction>,
+) -> Result<(), ImageStorageError> {
+    let Some(first) = frames.first() else {
+        return Ok(());
+    };
+
+    let mut encoder = png_encoder(
+        BufWriter::new(File::create(path)?),
+        first.width(),
+        first.height(),
+        png::BitDepth::Eight,
+        settings,
+        transfer_function,
+    );
+    encoder.set_animated(frames.len() as u32, !looping as u32)?;
+    encoder.set_frame_delay(1, frame_rate.clamp(1, u16::MAX as u32) as u16)?;
+
+    let mut writer = encoder.write_header()?;
+    write_cicp(&mut writer, transfer_function)?;
+    for frame in &frames {
+        writer.write_image_data(frame.as_raw())?;
+    }
+    writer.finish()?;
+
+    Ok(())
+}
+
+/// Saves frames as an animated WebP file.
+///
+/// Each frame is encoded as a lossless still image, whose bitstream is then wrapped in an
+/// animation frame of the extended file format.
+#[cfg(feature = "webp")]
+pub(crate) fn write_webp(
+    path: &Path,
+    frames: Vec<image::RgbaImage>,
+    frame_rate: u32,
+    looping: bool,
+) -> Result<(), ImageStorageError> {
+    use image_webp::{ColorType, WebPEncoder};
+    use std::io::Write;
+
+    /// The size of the RIFF header of a WebP file, which precedes its first chunk.
+    const RIFF_HEADER_LEN: usize = 12;
+
+    fn chunk(name: &[u8; 4], data: &[u8]) -> Vec<u8> {
+        let mut chunk = Vec::with_capacity(data.len() + 9);
+        chunk.extend_from_slice(name);
+        chunk.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        chunk.extend_from_slice(data);
+        if data.len() % 2 == 1 {
+            chunk.push(0);
+        }
+        chunk
+    }
+
+    let u24 = |value: u32| <[u8; 3]>::try_from(&value.to_le_bytes()[..3]).unwrap();
+
+    let Some(first) = frames.first() else {
+        return Ok(());
+    };
+    let (width, height) = first.dimensions();
+
+    let mut vp8x = vec![0b0001_0010, 0, 0, 0];
+    vp8x.extend_from_slice(&u24(width - 1));
+    vp8x.extend_from_slice(&u24(height - 1));
+
+    // A transparent background, and a loop count of 0 for infinite loops.
+    let mut anim = vec![0; 4];
+    anim.extend_from_slice(&(!looping as u16).to_le_bytes());
+
+    let mut chunks = vec![chunk(b"VP8X", &vp8x), chunk(b"ANIM", &anim)];
+    // Durations are whole milliseconds. Each frame ends at its exact time rounded to milliseconds,
+    // so that the rounding errors don't add up, e.g. to 17, 16 and 17 ms at 60 fps.
+    let frame_rate = u64::from(frame_rate.max(1));
+    let end_time = |index: usize| (index as u64 * 1000 + frame_rate / 2) / frame_rate;
+    for (index, frame) in frames.iter().enumerate() {
+        let duration = (end_time(index + 1) - end_time(index)) as u32;
+
+        let mut still = Vec::new();
+        WebPEncoder::new(&mut still).encode(frame.as_raw(), width, height, ColorType::Rgba8)?;
+
+        // Frames cover the whole canvas and replace the previous frame without blending. The still
+        // image consists of a single `VP8L` chunk, which becomes the data of the frame.
+        let mut anmf = Vec::with_capacity(still.len());
+        anmf.extend_from_slice(&u24(0));
+        anmf.extend_from_slice(&u24(0));
+        anmf.extend_from_slice(&u24(width - 1));
+        anmf.extend_from_slice(&u24(height - 1));
+        anmf.extend_from_slice(&u24(duration));
+        anmf.push(0b10);
+        anmf.extend_from_slice(&still[RIFF_HEADER_LEN..]);
+
+        chunks.push(chunk(b"ANMF", &anmf));
+    }
+
+    let size = 4 + chunks.iter().map(Vec::len).sum::<usize>();
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(b"RIFF")?;
+    writer.write_all(&(size as u32).to_le_bytes())?;
+    writer.write_all(b"WEBP")?;
+    for chunk in &chunks {
+        writer.write_all(chunk)?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
@@ -1,8 +1,35 @@
+mod animation;
+mod capture;
+mod color;
+mod encoder;
 mod node;
+mod passes;
+mod pipe;
 mod plugin;
+#[cfg(feature = "prepass")]
+mod prepass;
+mod readback;
+mod readiness;
+mod sink;
 mod storage;
+mod threads;
 
+pub use animation::ImageExportAnimation;
+pub use capture::ImageExportCapture;
+pub use color::TransferFunction;
+pub use encoder::{
+    AnimationFormat, BitDepth, EncoderSettings, ExportFormat, ExrCompression, ExrPrecision,
+    ExrSettings, GifSettings, JpegSettings, PngCompression, PngFilter, PngSettings, TiffSettings,
+};
+pub use passes::{ImageExportPass, ImageExportPasses};
+pub use pipe::ImageExportPipe;
 pub use plugin::{
-    ExportThreads, GpuImageExportSource, ImageExport, ImageExportPlugin, ImageExportSettings,
-    ImageExportSource, ImageExportSystems,
+    GpuImageExportSource, ImageExport, ImageExportFinished, ImageExportPlugin, ImageExportRange,
+    ImageExportSettings, ImageExportSource, ImageExportStart, ImageExportState, ImageExportSystems,
+    ResizeBehavior,
 };
+#[cfg(feature = "prepass")]
+pub use prepass::ImageExportPrepass;
+pub use readiness::StartBehavior;
+pub use sink::{ExportSink, ExportedFrame, ImageExportMessages, ImageExportSink};
+pub use threads::{ExportThreadSettings, ExportThreads, QueueFullBehavior};
@@ -1,4 +1,4 @@
-use crate::GpuImageExportSource;
+use crate::{readback::ExportCopies, GpuImageExportSource};
 use bevy::{
     prelude::*,
     render::{
@@ -8,11 +8,15 @@ use bevy::{
         texture::GpuImage,
     },
 };
-use wgpu::{TexelCopyBufferInfo, TexelCopyBufferLayout};
+use wgpu::{Origin3d, TexelCopyBufferInfo, TexelCopyBufferLayout};
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
 pub struct ImageExportLabel;
 
+/// Copies the sources of active exporters, or their regions, to their readback buffers.
+///
+/// Issues one copy per entry of [`ExportCopies`], i.e. per source that is read back this frame,
+/// however many exporters share it.
 pub struct ImageExportNode;
 impl Node for ImageExportNode {
     fn run(
@@ -21,18 +25,29 @@ impl Node for ImageExportNode {
         render_context: &mut RenderContext,
         world: &World,
     ) -> Result<(), NodeRunError> {
-        for (_, source) in world
-            .resource::<RenderAssets<GpuImageExportSource>>()
-            .iter()
-        {
+        let sources = world.resource::<RenderAssets<GpuImageExportSource>>();
+
+        for copy in &world.resource::<ExportCopies>().0 {
+            let Some(source) = sources.get(copy.source) else {
+                continue;
+            };
+
             if let Some(gpu_image) = world
                 .resource::<RenderAssets<GpuImage>>()
                 .get(&source.source_handle)
             {
+                let mut texture = gpu_image.texture.as_image_copy();
+                texture.origin = Origin3d {
+                    x: source.origin.x,
+                    y: source.origin.y,
+                    z: 0,
+                };
+                texture.aspect = source.copy_aspect();
+
                 render_context.command_encoder().copy_texture_to_buffer(
-                    gpu_image.texture.as_image_copy(),
+                    texture,
                     TexelCopyBufferInfo {
-                        buffer: &source.buffer,
+                        buffer: &source.buffers[copy.slot],
                         layout: TexelCopyBufferLayout {
                             offset: 0,
                             bytes_per_row: Some(source.padded_bytes_per_row),
@@ -0,0 +1,47 @@
+use crate::{ExportFormat, ImageExportSource};
+use bevy::{prelude::*, render::extract_component::ExtractComponent};
+
+/// Additional sources that an [`ImageExport`](crate::ImageExport) saves along with its own, e.g.
+/// the depth and normals of the same camera.
+///
+/// Every frame of a pass is saved with the same frame number as the frame of the exporter, to a
+/// subdirectory of the [`output_dir`](crate::ImageExportSettings::output_dir) named after the
+/// pass. Passes only apply to exporters that save image sequences. EXR sequences can also store
+/// their passes as layers of the same files, see
+/// [`ExrSettings::combine_passes`](crate::ExrSettings::combine_passes).
+///
+/// ```ignore
+/// commands.spawn((
+///     ImageExport(export_sources.add(color_image)),
+///     ImageExportPasses(vec![
+///         ImageExportPass::new("depth", export_sources.add(depth_image)).format(ExportFormat::Exr),
+///     ]),
+/// ));
+/// ```
+#[derive(Component, ExtractComponent, Clone, Debug, Default)]
+pub struct ImageExportPasses(pub Vec<ImageExportPass>);
+
+#[derive(Clone, Debug)]
+pub struct ImageExportPass {
+    /// The name of the pass, which is also the name of its subdirectory.
+    pub name: String,
+    pub source: Handle<ImageExportSource>,
+    /// The file format of the pass, which may differ from the format of the exporter. Ignored
+    /// when the passes are combined into the files of the exporter.
+    pub format: ExportFormat,
+}
+
+impl ImageExportPass {
+    pub fn new(name: impl Into<String>, source: Handle<ImageExportSource>) -> Self {
+        Self {
+            name: name.into(),
+            source,
+            format: ExportFormat::default(),
+        }
+    }
+
+    pub fn format(mut self, format: ExportFormat) -> Self {
+        self.format = format;
+        self
+    }
+}
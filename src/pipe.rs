@@ -0,0 +1,215 @@
+use crate::{ExportSink, ExportThreads, ExportedFrame, ImageExportStart, ImageExportState};
+use bevy::{
+    platform::collections::HashMap,
+    prelude::*,
+    render::{extract_component::ExtractComponent, render_resource::TextureFormat},
+};
+use crossbeam_channel::Sender;
+use std::{
+    io::{self, Write},
+    process::{Command, Stdio},
+    sync::Arc,
+};
+
+/// Streams the frames of an [`ImageExport`](crate::ImageExport) to the standard input of an
+/// external process instead of saving them as image files.
+///
+/// Frames are written in order, without row padding and in the texel layout of the source
+/// texture. The placeholders `{width}`, `{height}` and `{pix_fmt}` in [`args`](Self::args) are
+/// replaced with the frame size and the matching FFmpeg pixel format before the process is
+/// spawned.
+///
+/// The process receives the end of its input once the export ends, i.e. when the exporter is
+/// [stopped](ImageExportState::Stopped), reaches the end of its
+/// [`ImageExportRange`](crate::ImageExportRange) or loses this component. Recording again spawns a
+/// new process.
+#[derive(Component, ExtractComponent, Clone, Debug)]
+pub struct ImageExportPipe {
+    /// The program that frames will be streamed to.
+    pub program: String,
+    /// The arguments passed to the program.
+    pub args: Vec<String>,
+}
+
+impl ImageExportPipe {
+    pub fn new(program: impl Into<String>) -> Self {
+        Self {
+            program: program.into(),
+            args: Vec::new(),
+        }
+    }
+
+    pub fn args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args.extend(args.into_iter().map(Into::into));
+        self
+    }
+
+    /// Encodes the frames to an H.264 video file with [FFmpeg](https://ffmpeg.org).
+    pub fn ffmpeg(path: impl Into<String>, framerate: u32) -> Self {
+        Self::new("ffmpeg").args([
+            "-y".into(),
+            "-f".into(),
+            "rawvideo".into(),
+            "-pixel_format".into(),
+            "{pix_fmt}".into(),
+            "-video_size".into(),
+            "{width}x{height}".into(),
+            "-framerate".into(),
+            framerate.to_string(),
+            "-i".into(),
+            "-".into(),
+            "-vcodec".into(),
+            "libx264".into(),
+            "-crf".into(),
+            "25".into(),
+            "-pix_fmt".into(),
+            "yuv420p".into(),
+            path.into(),
+        ])
+    }
+
+    fn command(&self, width: u32, height: u32, format: TextureFormat) -> io::Result<Command> {
+        let pixel_format = ffmpeg_pixel_format(format);
+
+        let mut command = Command::new(&self.program);
+        for arg in &self.args {
+            if arg.contains("{pix_fmt}") && pixel_format.is_none() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("no pixel format equivalent for {:?}", format),
+                ));
+            }
+
+            command.arg(
+                arg.replace("{width}", &width.to_string())
+                    .replace("{height}", &height.to_string())
+                    .replace("{pix_fmt}", pixel_format.unwrap_or_default()),
+            );
+        }
+
+        Ok(command)
+    }
+}
+
+/// Returns the name of the FFmpeg pixel format matching the texel layout of `format`.
+fn ffmpeg_pixel_format(format: TextureFormat) -> Option<&'static str> {
+    match format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => Some("rgba"),
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => Some("bgra"),
+        TextureFormat::Rgba16Unorm => Some("rgba64le"),
+        TextureFormat::Rgba16Float => Some("rgbaf16le"),
+        TextureFormat::Rgba32Float => Some("rgbaf32le"),
+        TextureFormat::Rgb10a2Unorm => Some("x2bgr10le"),
+        TextureFormat::R8Unorm => Some("gray"),
+        TextureFormat::R16Unorm | TextureFormat::Depth16Unorm => Some("gray16le"),
+        TextureFormat::R16Float => Some("grayf16le"),
+        TextureFormat::R32Float | TextureFormat::Depth32Float => Some("grayf32le"),
+        _ => None,
+    }
+}
+
+/// Handle to a running pipe process, fed by a dedicated writer thread.
+pub(crate) struct PipeWriter {
+    sender: Sender<ExportedFrame>,
+}
+
+impl PipeWriter {
+    fn spawn(
+        pipe: &ImageExportPipe,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        export_threads: &ExportThreads,
+    ) -> io::Result<Self> {
+        let mut child = pipe
+            .command(width, height, format)?
+            .stdin(Stdio::piped())
+            .spawn()?;
+        let mut stdin = child.stdin.take().unwrap();
+        let (sender, receiver) = export_threads.bounded::<ExportedFrame>();
+        let program = pipe.program.clone();
+
+        export_threads.spawn(move || {
+            let mut result = Ok(());
+            for frame in receiver {
+                result = stdin.write_all(&frame.image_bytes);
+
+                if result.is_err() {
+                    break;
+                }
+            }
+
+            if let Err(err) = result {
+                error!({ error = %err, program = %program }, "failed writing frame to pipe");
+            }
+
+            drop(stdin);
+            match child.wait() {
+                Ok(status) if !status.success() => {
+                    error!({ %status, program = %program }, "pipe process exited unsuccessfully");
+                }
+                Err(err) => {
+                    error!({ error = %err, program = %program }, "failed waiting for pipe process")
+                }
+                _ => {}
+            }
+        });
+
+        Ok(Self { sender })
+    }
+}
+
+impl ExportSink for PipeWriter {
+    fn write_frame(&self, frame: ExportedFrame, export_threads: &ExportThreads) {
+        // The writer thread reports its own errors once it stops receiving.
+        export_threads.send(&self.sender, frame);
+    }
+}
+
+/// The pipe processes of all exporters in the render world.
+///
+/// A process is spawned when the first frame of its exporter is ready and closed once the export
+/// ends.
+#[derive(Resource, Default)]
+pub(crate) struct PipeWriters(HashMap<Entity, Option<Arc<PipeWriter>>>);
+
+impl PipeWriters {
+    /// Returns the writer of `entity`, spawning its process for frames of the given size and
+    /// format if necessary.
+    pub(crate) fn get_or_spawn(
+        &mut self,
+        entity: Entity,
+        pipe: &ImageExportPipe,
+        width: u32,
+        height: u32,
+        format: TextureFormat,
+        export_threads: &ExportThreads,
+    ) -> Option<Arc<PipeWriter>> {
+        self.0
+            .entry(entity)
+            .or_insert_with(|| {
+                PipeWriter::spawn(pipe, width, height, format, export_threads)
+                    .map(Arc::new)
+                    .inspect_err(|err| {
+                        error!({ error = %err, program = %pipe.program }, "failed spawning pipe process");
+                    })
+                    .ok()
+            })
+            .clone()
+    }
+}
+
+/// Closes the pipes of exporters that have been stopped or lost their [`ImageExportPipe`].
+///
+/// Frames that are still being read back keep their writer alive, so the process receives the end
+/// of its input after the last frame.
+pub(crate) fn close_pipes(
+    mut writers: ResMut<PipeWriters>,
+    pipes: Query<&ImageExportState, (With<ImageExportPipe>, With<ImageExportStart>)>,
+) {
+    writers.0.retain(|entity, _| {
+        pipes
+            .get(*entity)
+            .is_ok_and(|state| *state != ImageExportState::Stopped)
+    });
+}
@@ -1,54 +1,221 @@
 use crate::{
+    animation::{close_animations, AnimationWriters},
+    capture::{clear_captures, TakenCaptureReceiver, TakenCaptures},
     node::{ImageExportLabel, ImageExportNode},
-    storage::save_image,
+    pipe::{close_pipes, ImageExportPipe, PipeWriters},
+    readback::{
+        map_readbacks, prepare_export_copies, resize_export_sources, ExportCopies, Readbacks,
+        READBACK_BUFFER_COUNT,
+    },
+    readiness::{
+        receive_ready_exporters, report_ready_exporters, ReadyExporters, ReadyReceiver,
+        ReadySender, StartBehavior, WarmUpFrames,
+    },
+    sink::{receive_frame_messages, FrameMessageReceiver, FrameMessageSender},
+    storage::{check_animation_format, check_export_format, check_file_name},
+    EncoderSettings, ExportFormat, ExportThreadSettings, ExportThreads, ExportedFrame,
+    ImageExportAnimation, ImageExportCapture, ImageExportMessages, ImageExportPasses,
+    ImageExportSink, TransferFunction,
 };
 use bevy::{
     asset::RenderAssetUsages,
-    ecs::system::{lifetimeless::SRes, SystemParamItem},
+    ecs::{
+        entity::{EntityHashMap, EntityHashSet},
+        system::{lifetimeless::SRes, SystemParamItem},
+    },
     prelude::*,
     render::{
         extract_component::{ExtractComponent, ExtractComponentPlugin},
         graph::CameraDriverLabel,
         render_asset::{PrepareAssetError, RenderAsset, RenderAssetPlugin, RenderAssets},
         render_graph::RenderGraph,
-        render_resource::{Buffer, BufferDescriptor, BufferUsages, Extent3d, MapMode},
+        render_resource::{
+            Buffer, BufferDescriptor, BufferUsages, Extent3d, TextureAspect, TextureFormat,
+        },
         renderer::RenderDevice,
         texture::GpuImage,
         Render, RenderApp, RenderSystems,
     },
+    time::TimeUpdateStrategy,
 };
-use futures::channel::oneshot;
-use std::sync::{
-    atomic::{AtomicUsize, Ordering},
-    Arc,
+use std::{
+    ops::RangeInclusive,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use wgpu::PollType;
 
+/// An image that frames are exported from.
 #[derive(Asset, Reflect, Clone, Default)]
-pub struct ImageExportSource(pub Handle<Image>);
+pub struct ImageExportSource {
+    pub image: Handle<Image>,
+    /// The part of the image that is exported, in pixels. Only this region is copied and read
+    /// back, and it is clipped to the image. Exports the whole image if `None`.
+    pub region: Option<URect>,
+}
+
+impl ImageExportSource {
+    pub fn new(image: Handle<Image>) -> Self {
+        Self {
+            image,
+            region: None,
+        }
+    }
+
+    /// Exports only the given region of the image. See [`region`](Self::region).
+    pub fn with_region(mut self, region: URect) -> Self {
+        self.region = Some(region);
+        self
+    }
+
+    /// Returns the region of an image of the given size that is exported.
+    pub fn frame_rect(&self, image_size: UVec2) -> URect {
+        frame_rect(self.region, image_size)
+    }
+}
 
 impl From<Handle<Image>> for ImageExportSource {
     fn from(value: Handle<Image>) -> Self {
-        Self(value)
+        Self::new(value)
     }
 }
 
+fn frame_rect(region: Option<URect>, image_size: UVec2) -> URect {
+    let image_rect = URect::from_corners(UVec2::ZERO, image_size);
+    region.map_or(image_rect, |region| region.intersect(image_rect))
+}
+
 #[derive(Component, ExtractComponent, Clone, Debug)]
 pub struct ImageExportSettings {
     /// The directory that image files will be saved to.
     pub output_dir: String,
-    /// The image file extension. E.g. "png", "jpeg", or "exr".
-    pub extension: String,
+    /// The file format, which must be enabled by its cargo feature.
+    pub format: ExportFormat,
+    /// The template for file paths relative to [`output_dir`](Self::output_dir), which may
+    /// include subdirectories. Supports the following placeholders:
+    ///
+    /// - `{frame}`: The frame number. Use e.g. `{frame:06}` to pad it with zeros to six digits.
+    /// - `{name}`: The [`Name`] of the exporter entity, or its ID if it has none.
+    /// - `{entity}`: The ID of the exporter entity.
+    /// - `{timestamp}`: The time the export started, in seconds since the Unix epoch.
+    /// - `{ext}`: The [extension](ExportFormat::extension) of the [`format`](Self::format).
+    ///
+    /// Exporters with other placeholders, or unclosed ones, are rejected with an error before
+    /// they start.
+    pub file_name: String,
+    /// Added to the frame number in file names, e.g. to continue a previous export.
+    pub frame_offset: u64,
+    /// Options for the encoder of the chosen file format.
+    pub encoder: EncoderSettings,
+    /// Scales frames down to fit within this size before they are saved, keeping their aspect
+    /// ratio. Frames that already fit are saved at their original size.
+    pub max_size: Option<UVec2>,
+    /// Encodes the color values of saved files and animations with this transfer function, and
+    /// marks PNG files with it. If `None`, frames keep the values of their texture, except that
+    /// float textures are encoded with sRGB in formats that don't keep HDR values.
+    ///
+    /// [`ImageExportPasses`](crate::ImageExportPasses) keep the values of their textures, since
+    /// they usually don't hold colors.
+    pub transfer_function: Option<TransferFunction>,
+    /// What happens to frames when the source image is resized during an export.
+    pub on_resize: ResizeBehavior,
+}
+
+/// What happens to the frames of an [`ImageExport`] when its source image is resized.
+///
+/// [`ImageExportPipe`]s and [`ImageExportAnimation`]s always reject frames of a different size,
+/// since their frames share a fixed size.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ResizeBehavior {
+    /// Exports frames at the new size.
+    #[default]
+    Follow,
+    /// Skips frames whose size differs from the size of the source when the export started. Their
+    /// frame numbers are left out.
+    Reject,
 }
 
 pub struct GpuImageExportSource {
-    pub buffer: Buffer,
+    /// A ring of buffers that frames are copied to, so that reading one back doesn't block
+    /// rendering the next.
+    pub buffers: Vec<Buffer>,
     pub source_handle: Handle<Image>,
+    /// The [region](ImageExportSource::region) of the source.
+    pub region: Option<URect>,
+    /// The size of the source texture.
+    pub texture_size: Extent3d,
+    /// The position of the copied region in the source texture.
+    pub origin: UVec2,
+    /// The size of the copied region, i.e. of the exported frames.
     pub source_size: Extent3d,
+    pub format: TextureFormat,
     pub bytes_per_row: u32,
     pub padded_bytes_per_row: u32,
 }
 
+impl GpuImageExportSource {
+    /// Reallocates the readback buffers and the row layout for a new size of the source texture.
+    ///
+    /// The buffers fit the [`region`](Self::region) of the source, clipped to the texture.
+    ///
+    /// Readbacks that are still in flight keep the buffers they were copied to. Textures that
+    /// can't be copied to a buffer are rejected with an error, leaving nothing to copy.
+    pub(crate) fn resize(&mut self, device: &RenderDevice, texture_size: Extent3d) {
+        let rect = frame_rect(
+            self.region,
+            UVec2::new(texture_size.width, texture_size.height),
+        );
+        let mut size = Extent3d {
+            width: rect.width(),
+            height: rect.height(),
+            depth_or_array_layers: 1,
+        };
+
+        let format = self.format;
+        let block_size = format.block_copy_size(Some(self.copy_aspect()));
+        if block_size.is_none() {
+            error!(
+                "can't export {:?} textures, which can't be copied to buffers",
+                format
+            );
+            size.width = 0;
+            size.height = 0;
+        }
+
+        self.bytes_per_row = (size.width / format.block_dimensions().0) * block_size.unwrap_or(0);
+        self.padded_bytes_per_row =
+            RenderDevice::align_copy_bytes_per_row(self.bytes_per_row as usize) as u32;
+        self.texture_size = texture_size;
+        self.origin = rect.min;
+        self.source_size = size;
+        self.buffers = (0..READBACK_BUFFER_COUNT)
+            .map(|_| {
+                device.create_buffer(&BufferDescriptor {
+                    label: Some("Image Export Buffer"),
+                    size: (size.height * self.padded_bytes_per_row) as u64,
+                    usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
+                    mapped_at_creation: false,
+                })
+            })
+            .collect();
+    }
+
+    /// The aspect of the source texture that is copied, i.e. only the depth of depth textures.
+    pub(crate) fn copy_aspect(&self) -> TextureAspect {
+        if self.format.has_depth_aspect() {
+            TextureAspect::DepthOnly
+        } else {
+            TextureAspect::All
+        }
+    }
+
+    /// The format of the exported frames, i.e. of the copied aspect of the source texture, e.g.
+    /// `Depth32Float` for `Depth32FloatStencil8` textures.
+    pub(crate) fn frame_format(&self) -> TextureFormat {
+        self.format
+            .aspect_specific_format(self.copy_aspect())
+            .unwrap_or(self.format)
+    }
+}
+
 impl RenderAsset for GpuImageExportSource {
     type SourceAsset = ImageExportSource;
     type Param = (SRes<RenderDevice>, SRes<RenderAssets<GpuImage>>);
@@ -63,31 +230,24 @@ impl RenderAsset for GpuImageExportSource {
         (device, images): &mut SystemParamItem<Self::Param>,
         _previous_asset: Option<&Self>,
     ) -> Result<Self, PrepareAssetError<Self::SourceAsset>> {
-        let Some(gpu_image) = images.get(&source_asset.0) else {
+        let Some(gpu_image) = images.get(&source_asset.image) else {
             return Err(PrepareAssetError::RetryNextUpdate(source_asset));
         };
 
-        let size = gpu_image.texture.size();
-        let format = &gpu_image.texture_format;
-        let bytes_per_row =
-            (size.width / format.block_dimensions().0) * format.block_copy_size(None).unwrap();
-        let padded_bytes_per_row =
-            RenderDevice::align_copy_bytes_per_row(bytes_per_row as usize) as u32;
-
-        let source_size = gpu_image.texture.size();
-
-        Ok(GpuImageExportSource {
-            buffer: device.create_buffer(&BufferDescriptor {
-                label: Some("Image Export Buffer"),
-                size: (source_size.height * padded_bytes_per_row) as u64,
-                usage: BufferUsages::COPY_DST | BufferUsages::MAP_READ,
-                mapped_at_creation: false,
-            }),
-            source_handle: source_asset.0,
-            source_size,
-            bytes_per_row,
-            padded_bytes_per_row,
-        })
+        let mut source = GpuImageExportSource {
+            buffers: Vec::new(),
+            source_handle: source_asset.image,
+            region: source_asset.region,
+            texture_size: Extent3d::default(),
+            origin: UVec2::ZERO,
+            source_size: Extent3d::default(),
+            format: gpu_image.texture_format,
+            bytes_per_row: 0,
+            padded_bytes_per_row: 0,
+        };
+        source.resize(device, gpu_image.texture.size());
+
+        Ok(source)
     }
 
     fn byte_len(_: &Self::SourceAsset) -> Option<usize> {
@@ -95,133 +255,363 @@ impl RenderAsset for GpuImageExportSource {
     }
 }
 
+/// Added to an [`ImageExport`] once it has started, i.e. once its source has been rendered to.
+///
+/// Records when and under which name the exporter started, and the number of the frame it records
+/// in the current update. Systems that animate a scene per exported frame should run after
+/// [`ImageExportSystems::ImageExportSetup`] to see the current frame.
 #[derive(Component, ExtractComponent, Clone, Debug)]
-pub struct ImageExportStartFrame(u64);
+pub struct ImageExportStart {
+    pub(crate) frame_id: u64,
+    pub(crate) timestamp: u64,
+    pub(crate) name: String,
+    /// The size of the exported region of the source when the export started.
+    pub(crate) size: UVec2,
+}
+
+impl ImageExportStart {
+    /// The number of the frame recorded in the current update, starting at 1. It doesn't advance
+    /// while the exporter is [paused](ImageExportState::Paused).
+    pub fn frame_id(&self) -> u64 {
+        self.frame_id
+    }
+
+    /// The time the export started, in seconds since the Unix epoch.
+    pub fn timestamp(&self) -> u64 {
+        self.timestamp
+    }
+}
 
 impl Default for ImageExportSettings {
     fn default() -> Self {
         Self {
             output_dir: "out".into(),
-            extension: "png".into(),
+            format: ExportFormat::default(),
+            file_name: "{frame:05}.{ext}".into(),
+            frame_offset: 0,
+            encoder: EncoderSettings::default(),
+            max_size: None,
+            transfer_function: None,
+            on_resize: ResizeBehavior::default(),
         }
     }
 }
 
-fn setup_exporters(
-    mut commands: Commands,
-    exporters: Query<Entity, (With<ImageExportSettings>, Without<ImageExportStartFrame>)>,
-    mut frame_id: Local<u64>,
-) {
-    *frame_id = frame_id.wrapping_add(1);
-    for entity in &exporters {
-        commands
-            .entity(entity)
-            .insert(ImageExportStartFrame(*frame_id));
-    }
+/// The recording state of an [`ImageExport`].
+#[derive(Component, ExtractComponent, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ImageExportState {
+    /// Exports every rendered frame.
+    #[default]
+    Recording,
+    /// Skips frames without ending the export. Recording again continues the frame numbering.
+    Paused,
+    /// Ends the export, which also closes its [`ImageExportPipe`]. Recording again starts a new
+    /// export, whose frame numbering starts at 1.
+    Stopped,
 }
 
-#[derive(Component, ExtractComponent, Clone, Default, Debug)]
-#[require(ImageExportSettings)]
-pub struct ImageExport(pub Handle<ImageExportSource>);
+/// Limits an [`ImageExport`] to a range of its frames.
+///
+/// Frames are numbered from 1 as they are recorded, and keep their numbers in file names. Once the
+/// end of the range has been exported, the exporter is [stopped](ImageExportState::Stopped) and
+/// sends an [`ImageExportFinished`] message.
+#[derive(Component, ExtractComponent, Clone, Debug, PartialEq, Eq)]
+pub struct ImageExportRange {
+    /// The first frame to export.
+    pub start: u64,
+    /// The last frame to export, if any.
+    pub end: Option<u64>,
+    /// Exports every `stride`th frame, counting from [`start`](Self::start).
+    pub stride: u64,
+}
 
-#[derive(Default, Clone, Resource)]
-pub struct ExportThreads {
-    count: Arc<AtomicUsize>,
+impl Default for ImageExportRange {
+    fn default() -> Self {
+        Self {
+            start: 1,
+            end: None,
+            stride: 1,
+        }
+    }
 }
 
-impl ExportThreads {
-    /// Returns the number of threads currently running.
-    pub fn thread_count(&self) -> usize {
-        self.count.load(Ordering::SeqCst)
+impl ImageExportRange {
+    /// Exports the frames from `start` to `end`, inclusively.
+    pub fn new(frames: RangeInclusive<u64>) -> Self {
+        Self {
+            start: *frames.start(),
+            end: Some(*frames.end()),
+            ..default()
+        }
     }
 
-    /// Checks if all threads have finished.
-    pub fn is_finished(&self) -> bool {
-        self.thread_count() == 0
+    /// Exports every frame from `start` onwards.
+    pub fn starting_at(start: u64) -> Self {
+        Self { start, ..default() }
     }
 
-    /// Blocks the main thread until all frames have been saved successfully.
-    pub fn finish(&self) {
-        while !self.is_finished() {
-            std::thread::sleep(std::time::Duration::from_secs_f32(0.25));
-        }
+    pub fn stride(mut self, stride: u64) -> Self {
+        self.stride = stride;
+        self
     }
 
-    pub(crate) fn report_thread_started(&self) {
-        self.count.fetch_add(1, Ordering::SeqCst);
+    /// Checks if the frame with the given number should be exported.
+    pub fn contains(&self, frame_id: u64) -> bool {
+        frame_id >= self.start
+            && self.end.is_none_or(|end| frame_id <= end)
+            && (frame_id - self.start).is_multiple_of(self.stride.max(1))
     }
 }
 
-fn save_buffer_to_disk(
-    export_bundles: Query<(&ImageExport, &ImageExportSettings, &ImageExportStartFrame)>,
-    sources: Res<RenderAssets<GpuImageExportSource>>,
-    render_device: Res<RenderDevice>,
-    export_threads: Res<ExportThreads>,
-    mut frame_id: Local<u64>,
+/// Sent when an [`ImageExport`] has exported the last frame of its [`ImageExportRange`].
+///
+/// The frame may still be read back or saved when this is sent. Use [`ExportThreads::finish`]
+/// before exiting to wait for it.
+#[derive(Message, Clone, Copy, Debug)]
+pub struct ImageExportFinished {
+    /// The [`ImageExport`] entity.
+    pub entity: Entity,
+}
+
+/// Counts the frames of running exporters and ends stopped ones.
+fn advance_exporters(
+    mut commands: Commands,
+    mut exporters: Query<(
+        Entity,
+        &mut ImageExportState,
+        &mut ImageExportStart,
+        Option<&ImageExportRange>,
+    )>,
+    mut finished: MessageWriter<ImageExportFinished>,
 ) {
-    *frame_id = frame_id.wrapping_add(1);
-    for (export, settings, start_frame) in &export_bundles {
-        if let Some(gpu_source) = sources.get(&export.0) {
-            let image_bytes = {
-                let slice = gpu_source.buffer.slice(..);
-
-                {
-                    let (mapping_tx, mapping_rx) = oneshot::channel();
-
-                    render_device.map_buffer(&slice, MapMode::Read, move |res| {
-                        mapping_tx.send(res).unwrap();
-                    });
-
-                    if render_device
-                        .poll(PollType::Wait {
-                            submission_index: None,
-                            timeout: None,
-                        })
-                        .is_err()
-                    {
-                        break;
+    for (entity, mut state, mut start, range) in &mut exporters {
+        if *state == ImageExportState::Recording {
+            start.frame_id += 1;
+
+            if range
+                .and_then(|range| range.end)
+                .is_some_and(|end| start.frame_id > end)
+            {
+                *state = ImageExportState::Stopped;
+                finished.write(ImageExportFinished { entity });
+            }
+        }
+
+        if *state == ImageExportState::Stopped {
+            commands.entity(entity).remove::<ImageExportStart>();
+        }
+    }
+}
+
+/// The number of updates after which an exporter that still waits for its source to be rendered
+/// to logs a warning.
+const START_WARNING_UPDATES: u32 = 120;
+
+/// Starts new exporters.
+///
+/// Exporters wait until their source has been rendered to and the warm-up frames have passed, so
+/// that the first exported frame is never blank. Exporters that are still waiting after
+/// [`START_WARNING_UPDATES`] log a warning once, since no camera may be rendering to their source.
+///
+/// The formats of exporters are checked against the texture formats that the render world reports
+/// for their sources once they are ready, since images may only exist in the render world.
+/// Exporters that save image files are rejected with an error if their [`ExportFormat`] doesn't
+/// fit the texture format of their source, or the format of one of their [`ImageExportPasses`]
+/// doesn't fit the texture format of its source. Exporters that save image sequences are also
+/// rejected if their [`file_name`](ImageExportSettings::file_name) template is malformed.
+/// Exporters that save an [`ImageExportAnimation`] are rejected if its format is disabled.
+#[allow(clippy::type_complexity)]
+fn setup_exporters(
+    mut commands: Commands,
+    exporters: Query<
+        (
+            Entity,
+            &ImageExportSettings,
+            &ImageExportState,
+            Option<&Name>,
+            Has<ImageExportCapture>,
+            Has<ImageExportSink>,
+            Has<ImageExportPipe>,
+            Option<&ImageExportAnimation>,
+            Has<ImageExportMessages>,
+            Option<&ImageExportPasses>,
+        ),
+        (With<ImageExport>, Without<ImageExportStart>),
+    >,
+    warm_up: Res<WarmUpFrames>,
+    mut ready_exporters: ResMut<ReadyExporters>,
+    mut rejected: Local<EntityHashSet>,
+    mut waiting: Local<EntityHashMap<u32>>,
+) {
+    if exporters.is_empty() {
+        return;
+    }
+
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+
+    for (entity, settings, state, name, capture, sink, pipe, animation, messages, passes) in
+        &exporters
+    {
+        if *state == ImageExportState::Stopped {
+            continue;
+        }
+
+        // Passes only apply to image sequences, and wait for their images to be prepared as well.
+        // Combined passes are saved in the format of the exporter.
+        let saves_sequence = !(capture || sink || pipe || animation.is_some() || messages);
+        let combines_passes =
+            settings.format == ExportFormat::Exr && settings.encoder.exr.combine_passes;
+        let passes = passes
+            .filter(|_| saves_sequence)
+            .map_or(&[][..], |passes| &passes.0);
+        let Some((source, pass_formats)) = ready_exporters.sources(entity).and_then(|sources| {
+            let pass_formats = passes
+                .iter()
+                .zip(&sources.pass_formats)
+                .map(|(pass, texture_format)| {
+                    let format = if combines_passes {
+                        settings.format
+                    } else {
+                        pass.format
+                    };
+                    Some((format, (*texture_format)?))
+                })
+                .collect::<Option<Vec<_>>>()?;
+
+            (pass_formats.len() == passes.len()).then_some((sources, pass_formats))
+        }) else {
+            wait_for_start(&mut waiting, entity, *warm_up);
+            continue;
+        };
+
+        let result = if capture || saves_sequence {
+            std::iter::once((settings.format, source.format))
+                .chain(pass_formats)
+                .try_for_each(|(format, texture_format)| {
+                    check_export_format(format, &settings.encoder, texture_format)
+                })
+                .and_then(|()| {
+                    if saves_sequence {
+                        check_file_name(&settings.file_name)
+                    } else {
+                        Ok(())
                     }
+                })
+        } else if let Some(animation) = animation.filter(|_| !(sink || pipe)) {
+            check_animation_format(animation.format, source.format)
+        } else {
+            Ok(())
+        };
 
-                    futures_lite::future::block_on(mapping_rx).unwrap().unwrap();
-                }
-
-                slice.get_mapped_range().to_vec()
-            };
-
-            gpu_source.buffer.unmap();
-
-            let settings = settings.clone();
-            let frame_id = *frame_id - start_frame.0 + 1;
-            let bytes_per_row = gpu_source.bytes_per_row as usize;
-            let padded_bytes_per_row = gpu_source.padded_bytes_per_row as usize;
-            let source_size = gpu_source.source_size;
-            let export_threads = export_threads.clone();
-
-            export_threads.report_thread_started();
-            std::thread::spawn(move || {
-                if let Err(err) = save_image(
-                    &settings.output_dir,
-                    &settings.extension,
-                    image_bytes,
-                    bytes_per_row,
-                    padded_bytes_per_row,
-                    source_size.width,
-                    source_size.height,
-                    frame_id,
-                ) {
-                    error!({ error = %err }, "failed saving image to disk");
-                }
-
-                export_threads.count.fetch_sub(1, Ordering::SeqCst);
-            });
+        if let Err(err) = result {
+            if rejected.insert(entity) {
+                error!({ error = %err, %entity }, "failed starting image export");
+            }
+            continue;
         }
+
+        rejected.remove(&entity);
+        if !ready_exporters.is_warm(entity, *warm_up) {
+            wait_for_start(&mut waiting, entity, *warm_up);
+            continue;
+        }
+
+        let size = source.size;
+        waiting.remove(&entity);
+        ready_exporters.remove(entity);
+        commands.entity(entity).insert(ImageExportStart {
+            frame_id: (*state == ImageExportState::Recording) as u64,
+            timestamp,
+            name: name.map_or_else(|| entity.to_string(), |name| name.to_string()),
+            size,
+        });
+    }
+}
+
+/// Counts the updates that an exporter has been waiting to start, and warns once when it has been
+/// waiting for [`START_WARNING_UPDATES`] after its warm-up frames.
+fn wait_for_start(waiting: &mut EntityHashMap<u32>, entity: Entity, warm_up: WarmUpFrames) {
+    let updates = waiting.entry(entity).or_default();
+    *updates += 1;
+    if *updates == warm_up.0 + START_WARNING_UPDATES {
+        warn!(
+            { %entity },
+            "image export is still waiting for its source to be rendered to, use \
+            StartBehavior::WaitForSource if no camera renders to it"
+        );
     }
 }
 
+/// Exports the frames rendered to an [`ImageExportSource`].
+///
+/// Several exporters can share a source, e.g. to save a full-quality sequence and a downscaled
+/// preview with different [`ImageExportSettings`]. The source is then read back once per frame
+/// for all of them.
+#[derive(Component, ExtractComponent, Clone, Default, Debug)]
+#[require(ImageExportSettings, ImageExportState)]
+pub struct ImageExport(pub Handle<ImageExportSource>);
+
 /// Plugin enabling the generation of image sequences.
 #[derive(Default)]
 pub struct ImageExportPlugin {
     pub threads: ExportThreads,
+    /// Drives [`Time`] with a fixed delta of `1 / frame_rate` seconds per update, instead of the
+    /// wall clock.
+    ///
+    /// Use this for offline rendering, so that the exported sequence plays at this frame rate
+    /// however long each frame took to render and save. The maximum delta of [`Time<Virtual>`] is
+    /// raised to fit low frame rates. Must be positive and finite.
+    pub frame_rate: Option<f64>,
+    /// The number of additional updates that exporters wait for after their source has been
+    /// rendered to for the first time.
+    ///
+    /// Use this when the content of a source takes a few frames to appear, e.g. because of
+    /// temporal effects or assets that are still loading.
+    pub warm_up_frames: u32,
+    /// What exporters wait for before they start, i.e. whether a camera has to render to their
+    /// source first.
+    pub start_behavior: StartBehavior,
+}
+
+impl ImageExportPlugin {
+    /// Creates the plugin with a custom worker pool for saving frames.
+    pub fn new(thread_settings: ExportThreadSettings) -> Self {
+        Self {
+            threads: ExportThreads::new(thread_settings),
+            frame_rate: None,
+            warm_up_frames: 0,
+            start_behavior: StartBehavior::default(),
+        }
+    }
+
+    /// Advances [`Time`] by exactly `1 / frame_rate` seconds per update. See
+    /// [`frame_rate`](Self::frame_rate).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `frame_rate` isn't a positive, finite number.
+    pub fn with_frame_rate(mut self, frame_rate: f64) -> Self {
+        assert_valid_frame_rate(frame_rate);
+        self.frame_rate = Some(frame_rate);
+        self
+    }
+
+    /// Delays the start of exporters by `frames` updates. See
+    /// [`warm_up_frames`](Self::warm_up_frames).
+    pub fn with_warm_up_frames(mut self, frames: u32) -> Self {
+        self.warm_up_frames = frames;
+        self
+    }
+
+    /// Sets what exporters wait for before they start. See
+    /// [`start_behavior`](Self::start_behavior).
+    pub fn with_start_behavior(mut self, start_behavior: StartBehavior) -> Self {
+        self.start_behavior = start_behavior;
+        self
+    }
 }
 
 #[derive(Debug, Hash, PartialEq, Eq, Clone, SystemSet)]
@@ -229,10 +619,22 @@ pub enum ImageExportSystems {
     ImageExportSetup,
 }
 
+fn assert_valid_frame_rate(frame_rate: f64) {
+    assert!(
+        frame_rate.is_finite() && frame_rate > 0.0,
+        "the frame rate of ImageExportPlugin must be positive and finite, got {}",
+        frame_rate
+    );
+}
+
 impl Plugin for ImageExportPlugin {
     fn build(&self, app: &mut App) {
         use ImageExportSystems::*;
 
+        let (frame_sender, frame_receiver) = crossbeam_channel::unbounded();
+        let (ready_sender, ready_receiver) = crossbeam_channel::unbounded();
+        let (capture_sender, capture_receiver) = crossbeam_channel::unbounded();
+
         app.configure_sets(PostUpdate, ImageExportSetup)
             .register_type::<ImageExportSource>()
             .init_asset::<ImageExportSource>()
@@ -241,19 +643,68 @@ impl Plugin for ImageExportPlugin {
                 RenderAssetPlugin::<GpuImageExportSource>::default(),
                 ExtractComponentPlugin::<ImageExport>::default(),
                 ExtractComponentPlugin::<ImageExportSettings>::default(),
-                ExtractComponentPlugin::<ImageExportStartFrame>::default(),
+                ExtractComponentPlugin::<ImageExportStart>::default(),
+                ExtractComponentPlugin::<ImageExportState>::default(),
+                ExtractComponentPlugin::<ImageExportRange>::default(),
+                ExtractComponentPlugin::<ImageExportCapture>::default(),
+                ExtractComponentPlugin::<ImageExportPipe>::default(),
+                ExtractComponentPlugin::<ImageExportAnimation>::default(),
+                ExtractComponentPlugin::<ImageExportMessages>::default(),
+                ExtractComponentPlugin::<ImageExportPasses>::default(),
             ))
-            .add_systems(PostUpdate, setup_exporters.in_set(ImageExportSetup));
+            .add_message::<ExportedFrame>()
+            .add_message::<ImageExportFinished>()
+            .insert_resource(FrameMessageReceiver(frame_receiver))
+            .insert_resource(ReadyReceiver(ready_receiver))
+            .insert_resource(TakenCaptureReceiver(capture_receiver))
+            .insert_resource(WarmUpFrames(self.warm_up_frames))
+            .init_resource::<ReadyExporters>()
+            .add_systems(First, (receive_frame_messages, clear_captures))
+            .add_systems(
+                PostUpdate,
+                (advance_exporters, receive_ready_exporters, setup_exporters)
+                    .chain()
+                    .in_set(ImageExportSetup),
+            );
+
+        #[cfg(feature = "prepass")]
+        app.add_plugins(crate::prepass::ImageExportPrepassPlugin);
+
+        if let Some(frame_rate) = self.frame_rate {
+            assert_valid_frame_rate(frame_rate);
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1.0 / frame_rate,
+            )));
+        }
 
         let render_app = app.sub_app_mut(RenderApp);
 
         render_app
             .insert_resource(self.threads.clone())
+            .insert_resource(FrameMessageSender(frame_sender))
+            .insert_resource(ReadySender(ready_sender))
+            .insert_resource(self.start_behavior)
+            .insert_resource(TakenCaptures::new(capture_sender))
+            .insert_resource(Readbacks::new(self.threads.clone()))
+            .init_resource::<ExportCopies>()
+            .init_resource::<PipeWriters>()
+            .init_resource::<AnimationWriters>()
             .add_systems(
                 Render,
-                save_buffer_to_disk
-                    .after(RenderSystems::Render)
-                    .before(RenderSystems::Cleanup),
+                (
+                    (resize_export_sources, prepare_export_copies)
+                        .chain()
+                        .in_set(RenderSystems::PrepareResources),
+                    (
+                        map_readbacks,
+                        close_pipes,
+                        close_animations,
+                        report_ready_exporters,
+                    )
+                        .chain()
+                        .after(RenderSystems::Render)
+                        .before(RenderSystems::Cleanup),
+                ),
             );
 
         let mut graph = render_app
@@ -264,4 +715,20 @@ impl Plugin for ImageExportPlugin {
         graph.add_node(ImageExportLabel, ImageExportNode);
         graph.add_node_edge(CameraDriverLabel, ImageExportLabel);
     }
+
+    fn finish(&self, app: &mut App) {
+        // Virtual time clamps its delta to 250 ms by default, which would slow down frame rates
+        // below 4 fps. The time plugin may be added after this one, so this waits until all
+        // plugins have been built.
+        let Some(frame_rate) = self.frame_rate else {
+            return;
+        };
+
+        let delta = Duration::from_secs_f64(1.0 / frame_rate);
+        if let Some(mut time) = app.world_mut().get_resource_mut::<Time<Virtual>>() {
+            if time.max_delta() < delta {
+                time.set_max_delta(delta);
+            }
+        }
+    }
 }
@@ -0,0 +1,337 @@
+use bevy::{
+    asset::{embedded_asset, load_embedded_asset},
+    core_pipeline::{
+        core_3d::graph::{Core3d, Node3d},
+        prepass::ViewPrepassTextures,
+        FullscreenShader,
+    },
+    ecs::query::QueryItem,
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        render_asset::RenderAssets,
+        render_graph::{
+            NodeRunError, RenderGraphContext, RenderGraphExt, RenderLabel, ViewNode, ViewNodeRunner,
+        },
+        render_resource::{
+            binding_types::{
+                texture_2d, texture_2d_multisampled, texture_depth_2d,
+                texture_depth_2d_multisampled,
+            },
+            BindGroupEntries, BindGroupLayoutDescriptor, BindGroupLayoutEntries,
+            CachedRenderPipelineId, ColorTargetState, ColorWrites, Extent3d, FragmentState,
+            Operations, PipelineCache, RenderPassColorAttachment, RenderPassDescriptor,
+            RenderPipelineDescriptor, ShaderStages, SpecializedRenderPipeline,
+            SpecializedRenderPipelines, TextureDescriptor, TextureDimension, TextureFormat,
+            TextureSampleType, TextureUsages,
+        },
+        renderer::RenderContext,
+        texture::GpuImage,
+        view::Msaa,
+        Render, RenderApp, RenderStartup, RenderSystems,
+    },
+    shader::Shader,
+};
+
+/// Copies the prepass textures of a camera to images, so that they can be exported like any other
+/// image, e.g. as [`ImageExportPasses`](crate::ImageExportPasses) of the camera's exporter.
+///
+/// The camera needs the matching prepass components, i.e. [`DepthPrepass`], [`NormalPrepass`] or
+/// [`MotionVectorPrepass`]. The images must have the size of the camera's render target and the
+/// `RENDER_ATTACHMENT` and `COPY_SRC` usages, which [`image`](Self::image) takes care of.
+///
+/// - Depth is written to every color channel, e.g. of an `R32Float` image.
+/// - Normals are written as the prepass encodes them, i.e. mapped to `[0, 1]`.
+/// - Motion vectors are written to the red and green channels, e.g. of an `Rgba16Float` image.
+///
+/// Multisampled prepass textures are copied from their first sample.
+///
+/// [`DepthPrepass`]: bevy::core_pipeline::prepass::DepthPrepass
+/// [`NormalPrepass`]: bevy::core_pipeline::prepass::NormalPrepass
+/// [`MotionVectorPrepass`]: bevy::core_pipeline::prepass::MotionVectorPrepass
+#[derive(Component, ExtractComponent, Clone, Debug, Default)]
+pub struct ImageExportPrepass {
+    pub depth: Option<Handle<Image>>,
+    pub normal: Option<Handle<Image>>,
+    pub motion_vectors: Option<Handle<Image>>,
+}
+
+impl ImageExportPrepass {
+    /// Creates an image that a prepass texture of the given size can be copied to.
+    pub fn image(size: UVec2, format: TextureFormat) -> Image {
+        let size = Extent3d {
+            width: size.x,
+            height: size.y,
+            ..default()
+        };
+        let mut image = Image {
+            texture_descriptor: TextureDescriptor {
+                label: None,
+                size,
+                dimension: TextureDimension::D2,
+                format,
+                mip_level_count: 1,
+                sample_count: 1,
+                usage: TextureUsages::COPY_DST
+                    | TextureUsages::COPY_SRC
+                    | TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            },
+            ..default()
+        };
+        image.resize(size);
+
+        image
+    }
+
+    /// Returns the images that prepass textures are copied to.
+    pub(crate) fn images(&self) -> impl Iterator<Item = &Handle<Image>> {
+        self.targets().map(|(_, image)| image)
+    }
+
+    fn targets(&self) -> impl Iterator<Item = (PrepassTexture, &Handle<Image>)> {
+        [
+            (PrepassTexture::Depth, &self.depth),
+            (PrepassTexture::Normal, &self.normal),
+            (PrepassTexture::MotionVectors, &self.motion_vectors),
+        ]
+        .into_iter()
+        .filter_map(|(texture, image)| Some((texture, image.as_ref()?)))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum PrepassTexture {
+    Depth,
+    Normal,
+    MotionVectors,
+}
+
+#[derive(Resource)]
+struct PrepassCopyPipeline {
+    color_layout: BindGroupLayoutDescriptor,
+    color_multisampled_layout: BindGroupLayoutDescriptor,
+    depth_layout: BindGroupLayoutDescriptor,
+    depth_multisampled_layout: BindGroupLayoutDescriptor,
+    fullscreen_shader: FullscreenShader,
+    fragment_shader: Handle<Shader>,
+}
+
+impl PrepassCopyPipeline {
+    fn layout(&self, key: PrepassCopyPipelineKey) -> &BindGroupLayoutDescriptor {
+        match (key.texture, key.multisampled) {
+            (PrepassTexture::Depth, false) => &self.depth_layout,
+            (PrepassTexture::Depth, true) => &self.depth_multisampled_layout,
+            (_, false) => &self.color_layout,
+            (_, true) => &self.color_multisampled_layout,
+        }
+    }
+}
+
+fn init_prepass_copy_pipeline(
+    mut commands: Commands,
+    fullscreen_shader: Res<FullscreenShader>,
+    asset_server: Res<AssetServer>,
+) {
+    let layout = |label, entry| {
+        BindGroupLayoutDescriptor::new(
+            label,
+            &BindGroupLayoutEntries::single(ShaderStages::FRAGMENT, entry),
+        )
+    };
+    let sample_type = TextureSampleType::Float { filterable: false };
+
+    commands.insert_resource(PrepassCopyPipeline {
+        color_layout: layout("image_export_prepass_layout", texture_2d(sample_type)),
+        color_multisampled_layout: layout(
+            "image_export_prepass_multisampled_layout",
+            texture_2d_multisampled(sample_type),
+        ),
+        depth_layout: layout("image_export_prepass_depth_layout", texture_depth_2d()),
+        depth_multisampled_layout: layout(
+            "image_export_prepass_depth_multisampled_layout",
+            texture_depth_2d_multisampled(),
+        ),
+        fullscreen_shader: fullscreen_shader.clone(),
+        fragment_shader: load_embedded_asset!(asset_server.as_ref(), "prepass.wgsl"),
+    });
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct PrepassCopyPipelineKey {
+    texture: PrepassTexture,
+    multisampled: bool,
+    format: TextureFormat,
+}
+
+impl SpecializedRenderPipeline for PrepassCopyPipeline {
+    type Key = PrepassCopyPipelineKey;
+
+    fn specialize(&self, key: Self::Key) -> RenderPipelineDescriptor {
+        let mut shader_defs = Vec::new();
+        match key.texture {
+            PrepassTexture::Depth => shader_defs.push("DEPTH".into()),
+            PrepassTexture::MotionVectors => shader_defs.push("MOTION_VECTORS".into()),
+            PrepassTexture::Normal => {}
+        }
+        if key.multisampled {
+            shader_defs.push("MULTISAMPLED".into());
+        }
+
+        RenderPipelineDescriptor {
+            label: Some("image_export_prepass_pipeline".into()),
+            layout: vec![self.layout(key).clone()],
+            vertex: self.fullscreen_shader.to_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: self.fragment_shader.clone(),
+                shader_defs,
+                targets: vec![Some(ColorTargetState {
+                    format: key.format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+                ..default()
+            }),
+            ..default()
+        }
+    }
+}
+
+/// A copy of a prepass texture of a view to an image.
+struct PrepassCopy {
+    key: PrepassCopyPipelineKey,
+    pipeline: CachedRenderPipelineId,
+    target: AssetId<Image>,
+}
+
+#[derive(Component)]
+struct ViewPrepassCopies(Vec<PrepassCopy>);
+
+fn prepare_prepass_copies(
+    mut commands: Commands,
+    views: Query<(Entity, &ImageExportPrepass, &Msaa)>,
+    images: Res<RenderAssets<GpuImage>>,
+    copy_pipeline: Res<PrepassCopyPipeline>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<PrepassCopyPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+) {
+    for (entity, prepass, msaa) in &views {
+        let copies = prepass
+            .targets()
+            .filter_map(|(texture, image)| {
+                let key = PrepassCopyPipelineKey {
+                    texture,
+                    multisampled: msaa.samples() > 1,
+                    format: images.get(image)?.texture_format,
+                };
+
+                Some(PrepassCopy {
+                    key,
+                    pipeline: pipelines.specialize(&pipeline_cache, &copy_pipeline, key),
+                    target: image.id(),
+                })
+            })
+            .collect();
+
+        commands.entity(entity).insert(ViewPrepassCopies(copies));
+    }
+}
+
+#[derive(Debug, Hash, PartialEq, Eq, Clone, RenderLabel)]
+struct ImageExportPrepassLabel;
+
+#[derive(Default)]
+struct ImageExportPrepassNode;
+
+impl ViewNode for ImageExportPrepassNode {
+    type ViewQuery = (&'static ViewPrepassTextures, &'static ViewPrepassCopies);
+
+    fn run(
+        &self,
+        _graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        (textures, copies): QueryItem<Self::ViewQuery>,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let pipeline_cache = world.resource::<PipelineCache>();
+        let copy_pipeline = world.resource::<PrepassCopyPipeline>();
+        let images = world.resource::<RenderAssets<GpuImage>>();
+
+        for copy in &copies.0 {
+            let texture_view = match copy.key.texture {
+                PrepassTexture::Depth => textures.depth_view(),
+                PrepassTexture::Normal => textures.normal_view(),
+                PrepassTexture::MotionVectors => textures.motion_vectors_view(),
+            };
+
+            let (Some(texture_view), Some(target), Some(pipeline)) = (
+                texture_view,
+                images.get(copy.target),
+                pipeline_cache.get_render_pipeline(copy.pipeline),
+            ) else {
+                continue;
+            };
+
+            let bind_group = render_context.render_device().create_bind_group(
+                None,
+                &pipeline_cache.get_bind_group_layout(copy_pipeline.layout(copy.key)),
+                &BindGroupEntries::single(texture_view),
+            );
+
+            let mut render_pass =
+                render_context
+                    .command_encoder()
+                    .begin_render_pass(&RenderPassDescriptor {
+                        label: Some("image_export_prepass"),
+                        color_attachments: &[Some(RenderPassColorAttachment {
+                            view: &target.texture_view,
+                            depth_slice: None,
+                            resolve_target: None,
+                            ops: Operations::default(),
+                        })],
+                        depth_stencil_attachment: None,
+                        timestamp_writes: None,
+                        occlusion_query_set: None,
+                    });
+
+            render_pass.set_pipeline(pipeline);
+            render_pass.set_bind_group(0, &bind_group, &[]);
+            render_pass.draw(0..3, 0..1);
+        }
+
+        Ok(())
+    }
+}
+
+/// Copies prepass textures to images after the prepasses of each 3D camera.
+pub(crate) struct ImageExportPrepassPlugin;
+
+impl Plugin for ImageExportPrepassPlugin {
+    fn build(&self, app: &mut App) {
+        embedded_asset!(app, "prepass.wgsl");
+
+        app.add_plugins(ExtractComponentPlugin::<ImageExportPrepass>::default());
+
+        let render_app = app.sub_app_mut(RenderApp);
+
+        render_app
+            .init_resource::<SpecializedRenderPipelines<PrepassCopyPipeline>>()
+            .add_systems(RenderStartup, init_prepass_copy_pipeline)
+            .add_systems(
+                Render,
+                prepare_prepass_copies.in_set(RenderSystems::PrepareResources),
+            )
+            .add_render_graph_node::<ViewNodeRunner<ImageExportPrepassNode>>(
+                Core3d,
+                ImageExportPrepassLabel,
+            )
+            .add_render_graph_edges(
+                Core3d,
+                (
+                    Node3d::EndPrepasses,
+                    ImageExportPrepassLabel,
+                    Node3d::StartMainPass,
+                ),
+            );
+    }
+}
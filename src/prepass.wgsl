@@ -0,0 +1,28 @@
+#import bevy_core_pipeline::fullscreen_vertex_shader::FullscreenVertexOutput
+
+#ifdef DEPTH
+#ifdef MULTISAMPLED
+@group(0) @binding(0) var in_texture: texture_depth_multisampled_2d;
+#else
+@group(0) @binding(0) var in_texture: texture_depth_2d;
+#endif
+#else
+#ifdef MULTISAMPLED
+@group(0) @binding(0) var in_texture: texture_multisampled_2d<f32>;
+#else
+@group(0) @binding(0) var in_texture: texture_2d<f32>;
+#endif
+#endif
+
+// Multisampled textures are read from their first sample.
+@fragment
+fn fs_main(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    let value = textureLoad(in_texture, vec2<i32>(in.position.xy), 0);
+#ifdef DEPTH
+    return vec4(value, value, value, 1.0);
+#else ifdef MOTION_VECTORS
+    return vec4(value.rg, 0.0, 1.0);
+#else
+    return value;
+#endif
+}
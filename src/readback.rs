@@ -0,0 +1,491 @@
+use crate::{
+    animation::AnimationWriters,
+    capture::{CaptureSink, TakenCaptures},
+    pipe::PipeWriters,
+    plugin::ImageExportStart,
+    sink::FrameMessageSender,
+    storage::{FileSink, LayerSink, LayeredFile},
+    ExportFormat, ExportSink, ExportThreads, ExportedFrame, GpuImageExportSource, ImageExport,
+    ImageExportAnimation, ImageExportCapture, ImageExportMessages, ImageExportPasses,
+    ImageExportPipe, ImageExportRange, ImageExportSettings, ImageExportSink, ImageExportSource,
+    ImageExportState, ResizeBehavior,
+};
+use bevy::{
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    render::{
+        render_asset::RenderAssets,
+        render_resource::{Buffer, MapMode, TextureFormat},
+        renderer::RenderDevice,
+        sync_world::MainEntity,
+        texture::GpuImage,
+    },
+};
+use std::{
+    collections::VecDeque,
+    path::Path,
+    sync::{Arc, OnceLock},
+};
+use wgpu::{BufferAsyncError, PollType};
+
+/// The number of readback buffers per [`GpuImageExportSource`].
+///
+/// A frame is collected once its buffer has been mapped, which usually happens a frame or two
+/// after it was rendered. Rendering only blocks when the next buffer of a source is still in
+/// flight.
+pub(crate) const READBACK_BUFFER_COUNT: usize = 3;
+
+/// An exporter that receives the frame read back from a source.
+struct ReadbackTarget {
+    entity: Entity,
+    frame_id: u64,
+    sink: Arc<dyn ExportSink>,
+}
+
+/// A copy of a source texture into one of its readback buffers, recorded for the current frame.
+pub(crate) struct ExportCopy {
+    pub(crate) source: AssetId<ImageExportSource>,
+    pub(crate) slot: usize,
+    targets: Vec<ReadbackTarget>,
+}
+
+/// The copies that [`ImageExportNode`](crate::node::ImageExportNode) issues this frame.
+#[derive(Resource, Default)]
+pub(crate) struct ExportCopies(pub(crate) Vec<ExportCopy>);
+
+impl ExportCopies {
+    /// Adds a target to the copy of a source, recording the copy if it's the first target.
+    fn push(
+        &mut self,
+        readbacks: &mut Readbacks,
+        source_id: AssetId<ImageExportSource>,
+        source: &GpuImageExportSource,
+        target: ReadbackTarget,
+    ) {
+        // Exporters that share a source share its copy.
+        if let Some(copy) = self.0.iter_mut().find(|copy| copy.source == source_id) {
+            copy.targets.push(target);
+            return;
+        }
+
+        let next_slot = readbacks.next_slots.entry(source_id).or_default();
+        let slot = *next_slot;
+        *next_slot = (slot + 1) % READBACK_BUFFER_COUNT;
+
+        if readbacks.is_in_flight(&source.buffers[slot]) {
+            readbacks.wait();
+        }
+
+        self.0.push(ExportCopy {
+            source: source_id,
+            slot,
+            targets: vec![target],
+        });
+    }
+}
+
+struct PendingReadback {
+    buffer: Buffer,
+    mapping: Arc<OnceLock<Result<(), BufferAsyncError>>>,
+    width: u32,
+    height: u32,
+    format: TextureFormat,
+    bytes_per_row: usize,
+    padded_bytes_per_row: usize,
+    targets: Vec<ReadbackTarget>,
+}
+
+impl PendingReadback {
+    /// Copies the mapped buffer without row padding and hands it to the sinks of all targets.
+    fn finish(self, export_threads: &ExportThreads) {
+        let mut image_bytes = Vec::with_capacity(self.height as usize * self.bytes_per_row);
+        for padded_row in self
+            .buffer
+            .slice(..)
+            .get_mapped_range()
+            .chunks(self.padded_bytes_per_row)
+        {
+            image_bytes.extend_from_slice(&padded_row[..self.bytes_per_row]);
+        }
+
+        self.buffer.unmap();
+
+        let mut targets = self.targets.into_iter().peekable();
+        while let Some(target) = targets.next() {
+            let image_bytes = if targets.peek().is_some() {
+                image_bytes.clone()
+            } else {
+                std::mem::take(&mut image_bytes)
+            };
+
+            target.sink.write_frame(
+                ExportedFrame {
+                    entity: target.entity,
+                    frame_id: target.frame_id,
+                    width: self.width,
+                    height: self.height,
+                    format: self.format,
+                    image_bytes,
+                },
+                export_threads,
+            );
+        }
+    }
+}
+
+/// Tracks the readback buffers that have been copied to but not yet collected.
+///
+/// Readbacks that are still in flight when the render world is dropped are waited for, so the
+/// last frames of an export are not lost.
+#[derive(Resource)]
+pub(crate) struct Readbacks {
+    device: Option<RenderDevice>,
+    export_threads: ExportThreads,
+    pending: VecDeque<PendingReadback>,
+    next_slots: HashMap<AssetId<ImageExportSource>, usize>,
+}
+
+impl Readbacks {
+    pub(crate) fn new(export_threads: ExportThreads) -> Self {
+        Self {
+            device: None,
+            export_threads,
+            pending: VecDeque::new(),
+            next_slots: HashMap::default(),
+        }
+    }
+
+    fn is_in_flight(&self, buffer: &Buffer) -> bool {
+        self.pending
+            .iter()
+            .any(|readback| readback.buffer.id() == buffer.id())
+    }
+
+    /// Hands all mapped readbacks at the front of the queue to their sinks, in frame order.
+    fn collect(&mut self) {
+        while let Some(mapping) = self.pending.front().and_then(|r| r.mapping.get()) {
+            let mapping = mapping.clone();
+            let readback = self.pending.pop_front().unwrap();
+
+            match mapping {
+                Ok(()) => readback.finish(&self.export_threads),
+                Err(err) => error!({ error = %err }, "failed mapping image export buffer"),
+            }
+        }
+    }
+
+    /// Blocks until the GPU has finished all submitted work and collects every readback.
+    fn wait(&mut self) {
+        let Some(device) = &self.device else {
+            return;
+        };
+
+        let result = device.poll(PollType::Wait {
+            submission_index: None,
+            timeout: None,
+        });
+
+        if let Err(err) = result {
+            error!({ error = %err }, "failed waiting for image export buffers");
+            return;
+        }
+
+        self.collect();
+    }
+}
+
+impl Drop for Readbacks {
+    fn drop(&mut self) {
+        if !self.pending.is_empty() {
+            self.wait();
+        }
+    }
+}
+
+/// Reallocates the buffers of sources whose image has been resized.
+///
+/// Resizing an image doesn't change its [`ImageExportSource`], so the source isn't prepared
+/// again. Sources without an exporter are left alone until one is spawned.
+pub(crate) fn resize_export_sources(
+    exporters: Query<&ImageExport>,
+    mut sources: ResMut<RenderAssets<GpuImageExportSource>>,
+    images: Res<RenderAssets<GpuImage>>,
+    render_device: Res<RenderDevice>,
+) {
+    let source_ids = exporters
+        .iter()
+        .map(|export| export.0.id())
+        .collect::<HashSet<_>>();
+
+    for source_id in source_ids {
+        let Some(source) = sources.get_mut(source_id) else {
+            continue;
+        };
+
+        let Some(gpu_image) = images.get(&source.source_handle) else {
+            continue;
+        };
+
+        let size = gpu_image.texture.size();
+        if size != source.texture_size || gpu_image.texture_format != source.format {
+            source.format = gpu_image.texture_format;
+            source.resize(&render_device, size);
+        }
+    }
+}
+
+/// Records a copy for every source that an active exporter reads from this frame.
+///
+/// Only extracted exporters that are recording and within their range are considered, so sources
+/// without such an exporter aren't copied at all. Exporters that share a source share one copy and
+/// its readback, and so do the [`ImageExportPasses`] of exporters.
+#[allow(clippy::type_complexity, clippy::too_many_arguments)]
+pub(crate) fn prepare_export_copies(
+    exporters: Query<(
+        Entity,
+        &MainEntity,
+        &ImageExport,
+        &ImageExportSettings,
+        &ImageExportStart,
+        &ImageExportState,
+        Option<&ImageExportRange>,
+        Option<&ImageExportCapture>,
+        Option<&ImageExportSink>,
+        Option<&ImageExportPipe>,
+        Option<&ImageExportAnimation>,
+        Has<ImageExportMessages>,
+        Option<&ImageExportPasses>,
+    )>,
+    sources: Res<RenderAssets<GpuImageExportSource>>,
+    images: Res<RenderAssets<GpuImage>>,
+    render_device: Res<RenderDevice>,
+    export_threads: Res<ExportThreads>,
+    frame_messages: Res<FrameMessageSender>,
+    mut pipe_writers: ResMut<PipeWriters>,
+    mut animation_writers: ResMut<AnimationWriters>,
+    mut taken_captures: ResMut<TakenCaptures>,
+    mut readbacks: ResMut<Readbacks>,
+    mut copies: ResMut<ExportCopies>,
+) {
+    readbacks
+        .device
+        .get_or_insert_with(|| render_device.clone());
+    copies.0.clear();
+
+    for (
+        entity,
+        main_entity,
+        export,
+        settings,
+        start,
+        state,
+        range,
+        capture,
+        sink,
+        pipe,
+        animation,
+        messages,
+        passes,
+    ) in &exporters
+    {
+        if *state != ImageExportState::Recording
+            || range.is_some_and(|range| !range.contains(start.frame_id))
+        {
+            continue;
+        }
+
+        let source_id = export.0.id();
+        let Some(source) = sources.get(source_id) else {
+            continue;
+        };
+
+        if images.get(&source.source_handle).is_none() {
+            continue;
+        }
+
+        // Regions outside of the source texture have nothing to copy.
+        let size = UVec2::new(source.source_size.width, source.source_size.height);
+        if size.cmpeq(UVec2::ZERO).any() {
+            continue;
+        }
+
+        // Pipes and animations can't follow a resize, since their frames share a fixed size.
+        let fixed_size =
+            capture.is_none() && sink.is_none() && (pipe.is_some() || animation.is_some());
+        if size != start.size && (settings.on_resize == ResizeBehavior::Reject || fixed_size) {
+            continue;
+        }
+
+        let saves_sequence = capture.is_none()
+            && sink.is_none()
+            && pipe.is_none()
+            && animation.is_none()
+            && !messages;
+        let all_passes = passes
+            .filter(|_| saves_sequence)
+            .map_or(&[][..], |passes| &passes.0);
+        let passes = all_passes
+            .iter()
+            .filter_map(|pass| {
+                let pass_source = sources.get(&pass.source)?;
+                let has_frame = images.get(&pass_source.source_handle).is_some()
+                    && pass_source.source_size.width > 0
+                    && pass_source.source_size.height > 0;
+
+                has_frame.then_some((pass, pass_source))
+            })
+            .collect::<Vec<_>>();
+
+        // Combined passes are saved as the layers of one file, once all of them have been read
+        // back. The whole frame is skipped if a pass has nothing to copy, rather than saving a
+        // file that lacks its layer.
+        let combines_passes = !all_passes.is_empty()
+            && settings.format == ExportFormat::Exr
+            && settings.encoder.exr.combine_passes;
+        if combines_passes && passes.len() != all_passes.len() {
+            continue;
+        }
+
+        let layered_file = combines_passes.then(|| {
+            let names = std::iter::once(None)
+                .chain(passes.iter().map(|(pass, _)| Some(pass.name.clone())))
+                .collect();
+
+            Arc::new(LayeredFile::new(
+                FileSink {
+                    settings: settings.clone(),
+                    timestamp: start.timestamp,
+                    name: start.name.clone(),
+                },
+                names,
+            ))
+        });
+
+        let sink: Arc<dyn ExportSink> = if let Some(capture) = capture {
+            let Some(path) = taken_captures.take(entity, main_entity.id(), capture) else {
+                continue;
+            };
+            Arc::new(CaptureSink {
+                path,
+                format: settings.format,
+                encoder: settings.encoder.clone(),
+                max_size: settings.max_size,
+                transfer_function: settings.transfer_function,
+            })
+        } else if let Some(sink) = sink {
+            sink.0.clone()
+        } else if let Some(pipe) = pipe {
+            let Some(writer) = pipe_writers.get_or_spawn(
+                entity,
+                pipe,
+                size.x,
+                size.y,
+                source.frame_format(),
+                &export_threads,
+            ) else {
+                continue;
+            };
+            writer
+        } else if let Some(animation) = animation {
+            animation_writers.get_or_spawn(entity, animation, settings, &export_threads)
+        } else if messages {
+            Arc::new(frame_messages.clone())
+        } else if let Some(file) = &layered_file {
+            Arc::new(LayerSink {
+                file: file.clone(),
+                index: 0,
+            })
+        } else {
+            Arc::new(FileSink {
+                settings: settings.clone(),
+                timestamp: start.timestamp,
+                name: start.name.clone(),
+            })
+        };
+
+        copies.push(
+            &mut readbacks,
+            source_id,
+            source,
+            ReadbackTarget {
+                entity: main_entity.id(),
+                frame_id: start.frame_id,
+                sink,
+            },
+        );
+
+        for (index, (pass, pass_source)) in passes.into_iter().enumerate() {
+            let sink: Arc<dyn ExportSink> = if let Some(file) = &layered_file {
+                Arc::new(LayerSink {
+                    file: file.clone(),
+                    index: index + 1,
+                })
+            } else {
+                let mut settings = settings.clone();
+                settings.output_dir = Path::new(&settings.output_dir)
+                    .join(&pass.name)
+                    .to_string_lossy()
+                    .into_owned();
+                settings.format = pass.format;
+                settings.transfer_function = None;
+
+                Arc::new(FileSink {
+                    settings,
+                    timestamp: start.timestamp,
+                    name: start.name.clone(),
+                })
+            };
+
+            copies.push(
+                &mut readbacks,
+                pass.source.id(),
+                pass_source,
+                ReadbackTarget {
+                    entity: main_entity.id(),
+                    frame_id: start.frame_id,
+                    sink,
+                },
+            );
+        }
+    }
+}
+
+/// Starts mapping the buffers copied to this frame and collects the readbacks that are ready.
+pub(crate) fn map_readbacks(
+    sources: Res<RenderAssets<GpuImageExportSource>>,
+    render_device: Res<RenderDevice>,
+    mut readbacks: ResMut<Readbacks>,
+    mut copies: ResMut<ExportCopies>,
+) {
+    for copy in copies.0.drain(..) {
+        let Some(source) = sources.get(copy.source) else {
+            continue;
+        };
+
+        let buffer = source.buffers[copy.slot].clone();
+        let mapping = Arc::new(OnceLock::new());
+
+        render_device.map_buffer(&buffer.slice(..), MapMode::Read, {
+            let mapping = mapping.clone();
+            move |result| {
+                let _ = mapping.set(result);
+            }
+        });
+
+        readbacks.pending.push_back(PendingReadback {
+            buffer,
+            mapping,
+            width: source.source_size.width,
+            height: source.source_size.height,
+            format: source.frame_format(),
+            bytes_per_row: source.bytes_per_row as usize,
+            padded_bytes_per_row: source.padded_bytes_per_row as usize,
+            targets: copy.targets,
+        });
+    }
+
+    match render_device.poll(PollType::Poll) {
+        Ok(_) => readbacks.collect(),
+        Err(err) => error!({ error = %err }, "failed polling image export buffers"),
+    }
+}
@@ -0,0 +1,190 @@
+#[cfg(feature = "prepass")]
+use crate::ImageExportPrepass;
+use crate::{plugin::ImageExportStart, GpuImageExportSource, ImageExport, ImageExportPasses};
+use bevy::{
+    camera::NormalizedRenderTarget,
+    platform::collections::{HashMap, HashSet},
+    prelude::*,
+    render::{
+        camera::ExtractedCamera,
+        render_asset::RenderAssets,
+        render_resource::{PipelineCache, TextureFormat},
+        sync_world::MainEntity,
+    },
+};
+use crossbeam_channel::{Receiver, Sender};
+
+/// What an [`ImageExport`] waits for before it starts.
+///
+/// Either way, exporters also wait until no pipeline is waiting to be compiled, and then for the
+/// [`warm_up_frames`](crate::ImageExportPlugin::warm_up_frames).
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StartBehavior {
+    /// Waits until an active camera has drawn to the source, either as its render target or
+    /// through an [`ImageExportPrepass`](crate::ImageExportPrepass), so that the first exported
+    /// frame is never blank.
+    #[default]
+    WaitForCamera,
+    /// Waits only until the source image has been prepared in the render world.
+    ///
+    /// Use this for sources that no camera renders to, e.g. images written by compute passes,
+    /// images filled on the CPU, or images behind a
+    /// [`RenderTarget::TextureView`](bevy::camera::RenderTarget::TextureView).
+    WaitForSource,
+}
+
+/// Reports exporters whose source has been rendered to from the render world.
+///
+/// Reports are only meaningful while the main world runs, so a send error, which means that the
+/// main world has been dropped, is ignored.
+#[derive(Resource, Clone)]
+pub(crate) struct ReadySender(pub(crate) Sender<(Entity, ReadySources)>);
+
+#[derive(Resource)]
+pub(crate) struct ReadyReceiver(pub(crate) Receiver<(Entity, ReadySources)>);
+
+/// The sources of a ready exporter as they have been prepared in the render world.
+///
+/// Their formats are checked against these, since their images may only exist in the render world.
+#[derive(Clone, Debug)]
+pub(crate) struct ReadySources {
+    /// The texture format of the source.
+    pub(crate) format: TextureFormat,
+    /// The size of the exported region of the source.
+    pub(crate) size: UVec2,
+    /// The texture formats of the [`ImageExportPasses`] of the exporter, in order, or `None` for
+    /// passes whose source hasn't been prepared yet.
+    pub(crate) pass_formats: Vec<Option<TextureFormat>>,
+}
+
+impl ReadySources {
+    fn new(
+        source: &GpuImageExportSource,
+        passes: Option<&ImageExportPasses>,
+        sources: &RenderAssets<GpuImageExportSource>,
+    ) -> Self {
+        Self {
+            format: source.frame_format(),
+            size: UVec2::new(source.source_size.width, source.source_size.height),
+            pass_formats: passes.map_or(Vec::new(), |passes| {
+                passes
+                    .0
+                    .iter()
+                    .map(|pass| {
+                        sources
+                            .get(&pass.source)
+                            .map(GpuImageExportSource::frame_format)
+                    })
+                    .collect()
+            }),
+        }
+    }
+}
+
+/// The number of updates that have to pass after an exporter became ready before it starts.
+#[derive(Resource, Clone, Copy, Default)]
+pub(crate) struct WarmUpFrames(pub(crate) u32);
+
+/// Counts the updates in which the render world reported an exporter as ready, along with its
+/// latest reported sources.
+#[derive(Resource, Default)]
+pub(crate) struct ReadyExporters(HashMap<Entity, (u32, ReadySources)>);
+
+impl ReadyExporters {
+    /// Returns the sources of an exporter that has been reported as ready.
+    pub(crate) fn sources(&self, entity: Entity) -> Option<&ReadySources> {
+        self.0.get(&entity).map(|(_, sources)| sources)
+    }
+
+    /// Checks if an exporter has been ready for more than `warm_up` updates.
+    pub(crate) fn is_warm(&self, entity: Entity, warm_up: WarmUpFrames) -> bool {
+        self.0
+            .get(&entity)
+            .is_some_and(|&(frames, _)| frames > warm_up.0)
+    }
+
+    pub(crate) fn remove(&mut self, entity: Entity) {
+        self.0.remove(&entity);
+    }
+}
+
+/// Reports the exporters that haven't started yet once their source has been rendered to.
+///
+/// Runs after the render graph. With [`StartBehavior::WaitForCamera`], a source counts as rendered
+/// to once an active camera has drawn to its image in this frame, either as its render target or
+/// through an [`ImageExportPrepass`](crate::ImageExportPrepass), while no pipeline was waiting to
+/// be compiled, since cameras skip drawing with pipelines that aren't ready. With
+/// [`StartBehavior::WaitForSource`], it counts as rendered to once it has been prepared. Content
+/// that takes longer to appear, e.g. textures that are still loading, can be waited for with
+/// [`ImageExportPlugin::warm_up_frames`](crate::ImageExportPlugin::warm_up_frames).
+pub(crate) fn report_ready_exporters(
+    exporters: Query<
+        (&MainEntity, &ImageExport, Option<&ImageExportPasses>),
+        Without<ImageExportStart>,
+    >,
+    sources: Res<RenderAssets<GpuImageExportSource>>,
+    cameras: Query<&ExtractedCamera>,
+    #[cfg(feature = "prepass")] prepasses: Query<&ImageExportPrepass, With<ExtractedCamera>>,
+    pipeline_cache: Res<PipelineCache>,
+    start_behavior: Res<StartBehavior>,
+    ready: Res<ReadySender>,
+) {
+    if exporters.is_empty() || pipeline_cache.waiting_pipelines().next().is_some() {
+        return;
+    }
+
+    if *start_behavior == StartBehavior::WaitForSource {
+        for (main_entity, export, passes) in &exporters {
+            if let Some(source) = sources.get(&export.0) {
+                let _ = ready.0.send((
+                    main_entity.id(),
+                    ReadySources::new(source, passes, &sources),
+                ));
+            }
+        }
+        return;
+    }
+
+    // Cameras are only extracted while they are active, and the camera driver has run by now.
+    #[cfg_attr(not(feature = "prepass"), allow(unused_mut))]
+    let mut rendered_images = cameras
+        .iter()
+        .filter_map(|camera| match &camera.target {
+            Some(NormalizedRenderTarget::Image(target)) => Some(target.handle.id()),
+            _ => None,
+        })
+        .collect::<HashSet<_>>();
+
+    #[cfg(feature = "prepass")]
+    rendered_images.extend(
+        prepasses
+            .iter()
+            .flat_map(|prepass| prepass.images().map(Handle::id)),
+    );
+
+    for (main_entity, export, passes) in &exporters {
+        if let Some(source) = sources
+            .get(&export.0)
+            .filter(|source| rendered_images.contains(&source.source_handle.id()))
+        {
+            let _ = ready.0.send((
+                main_entity.id(),
+                ReadySources::new(source, passes, &sources),
+            ));
+        }
+    }
+}
+
+/// Counts the updates in which exporters were reported as ready.
+pub(crate) fn receive_ready_exporters(
+    receiver: Res<ReadyReceiver>,
+    mut ready_exporters: ResMut<ReadyExporters>,
+) {
+    for (entity, sources) in receiver.0.try_iter() {
+        let frames = ready_exporters
+            .0
+            .get(&entity)
+            .map_or(0, |&(frames, _)| frames);
+        ready_exporters.0.insert(entity, (frames + 1, sources));
+    }
+}
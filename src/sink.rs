@@ -0,0 +1,103 @@
+use crate::ExportThreads;
+use bevy::{
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::{
+        extract_component::ExtractComponent,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+    },
+};
+use crossbeam_channel::{Receiver, Sender};
+use std::sync::Arc;
+
+/// A frame that has been read back from the GPU.
+///
+/// Exporters with [`ImageExportMessages`] send their frames as messages of this type in the main
+/// world.
+#[derive(Message, Clone, Debug)]
+pub struct ExportedFrame {
+    /// The [`ImageExport`](crate::ImageExport) entity in the main world.
+    pub entity: Entity,
+    /// The frame number, starting at 1.
+    pub frame_id: u64,
+    pub width: u32,
+    pub height: u32,
+    /// The format of the exported texels, which determines the layout of
+    /// [`image_bytes`](Self::image_bytes). This is the format of the source texture, or of its
+    /// depth aspect for depth-stencil textures, e.g. `Depth32Float` for `Depth32FloatStencil8`.
+    pub format: TextureFormat,
+    /// The texel data of the frame, without row padding.
+    pub image_bytes: Vec<u8>,
+}
+
+impl ExportedFrame {
+    /// Converts the frame into an [`Image`] with the [`format`](Self::format) of the frame.
+    pub fn into_image(self) -> Image {
+        Image::new(
+            Extent3d {
+                width: self.width,
+                height: self.height,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            self.image_bytes,
+            self.format,
+            RenderAssetUsages::default(),
+        )
+    }
+}
+
+/// A destination for the frames of an [`ImageExport`](crate::ImageExport).
+///
+/// By default, every frame is saved as an image file according to
+/// [`ImageExportSettings`](crate::ImageExportSettings). Use [`ImageExportSink`] to choose a
+/// different sink.
+pub trait ExportSink: Send + Sync + 'static {
+    /// Receives a frame on the render thread, in frame order.
+    ///
+    /// Expensive work such as encoding should be moved off the render thread, e.g. with
+    /// [`ExportThreads::execute`], which also lets [`ExportThreads::finish`] wait for it.
+    fn write_frame(&self, frame: ExportedFrame, export_threads: &ExportThreads);
+}
+
+/// Sends the frames of an [`ImageExport`](crate::ImageExport) to a custom [`ExportSink`] instead
+/// of saving them as image files.
+#[derive(Component, ExtractComponent, Clone)]
+pub struct ImageExportSink(pub Arc<dyn ExportSink>);
+
+impl ImageExportSink {
+    pub fn new(sink: impl ExportSink) -> Self {
+        Self(Arc::new(sink))
+    }
+}
+
+/// Sends the frames of an [`ImageExport`](crate::ImageExport) as [`ExportedFrame`] messages in
+/// the main world instead of saving them as image files.
+///
+/// Frames arrive a few updates after they were rendered and can be read with a
+/// [`MessageReader<ExportedFrame>`].
+#[derive(Component, ExtractComponent, Clone, Default, Debug)]
+pub struct ImageExportMessages;
+
+/// Forwards frames from the render world to [`receive_frame_messages`].
+///
+/// Frames that are read back after the main world has been dropped have no one left to receive
+/// them as messages, so a send error is ignored.
+#[derive(Resource, Clone)]
+pub(crate) struct FrameMessageSender(pub(crate) Sender<ExportedFrame>);
+
+impl ExportSink for FrameMessageSender {
+    fn write_frame(&self, frame: ExportedFrame, _export_threads: &ExportThreads) {
+        let _ = self.0.send(frame);
+    }
+}
+
+#[derive(Resource)]
+pub(crate) struct FrameMessageReceiver(pub(crate) Receiver<ExportedFrame>);
+
+pub(crate) fn receive_frame_messages(
+    receiver: Res<FrameMessageReceiver>,
+    mut frames: MessageWriter<ExportedFrame>,
+) {
+    frames.write_batch(receiver.0.try_iter());
+}
@@ -1,6 +1,39 @@
-use bytemuck::AnyBitPattern;
-use image::{EncodableLayout, ImageBuffer, Pixel, PixelWithColorType, Rgba};
-use std::fs::create_dir_all;
+#[cfg(feature = "bmp")]
+use crate::encoder::write_bmp;
+#[cfg(feature = "hdr")]
+use crate::encoder::write_hdr;
+#[cfg(feature = "jpeg")]
+use crate::encoder::write_jpeg;
+#[cfg(feature = "png")]
+use crate::encoder::write_png;
+#[cfg(feature = "pnm")]
+use crate::encoder::write_pnm;
+#[cfg(feature = "qoi")]
+use crate::encoder::write_qoi;
+#[cfg(feature = "tga")]
+use crate::encoder::write_tga;
+#[cfg(feature = "tiff")]
+use crate::encoder::write_tiff;
+#[cfg(feature = "exr")]
+use crate::encoder::{write_exr, write_exr_layers};
+use crate::{
+    color::{linear_to_srgb, srgb_to_linear},
+    AnimationFormat, BitDepth, EncoderSettings, ExportFormat, ExportSink, ExportThreads,
+    ExportedFrame, ImageExportSettings, TransferFunction,
+};
+use bevy::{
+    ecs::entity::Entity,
+    log::{error, warn},
+    math::UVec2,
+    render::render_resource::TextureFormat,
+};
+use half::f16;
+use image::{imageops::FilterType, DynamicImage, GrayImage, ImageBuffer, Rgba32FImage, RgbaImage};
+use std::{
+    fs::create_dir_all,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
 
 #[derive(Debug, thiserror::Error)]
 pub enum ImageStorageError {
@@ -10,65 +43,593 @@ pub enum ImageStorageError {
     SaveImageBuffer(#[from] image::ImageError),
     #[error("Failed to create image buffer")]
     BufferCreation,
+    #[error("Unsupported texture format: {0:?}")]
+    UnsupportedTextureFormat(TextureFormat),
+    #[error("Saving {0:?} files requires the `{feature}` cargo feature", feature = .0.feature())]
+    FormatDisabled(ExportFormat),
+    #[error("Invalid file name template: {0}")]
+    InvalidFileName(String),
+    #[error("The {0} pass doesn't have the size of the exporter's frames")]
+    LayerSizeMismatch(String),
+    #[error("Saving {0:?} animations requires the `{feature}` cargo feature", feature = .0.feature())]
+    AnimationFormatDisabled(AnimationFormat),
+    #[cfg(feature = "exr")]
+    #[error("Failed to save EXR image: {0}")]
+    Exr(#[from] exr::error::Error),
+    #[cfg(any(feature = "png", feature = "apng"))]
+    #[error("Failed to save PNG image: {0}")]
+    Png(#[from] png::EncodingError),
+    #[cfg(feature = "webp")]
+    #[error("Failed to save WebP animation: {0}")]
+    WebP(#[from] image_webp::EncodingError),
 }
 
-fn save_buffer<P: Pixel + PixelWithColorType>(
-    image_bytes: &[P::Subpixel],
+/// Checks that frames of a source texture with the given format can be saved as `format`.
+///
+/// Saving an HDR texture in an 8-bit or 16-bit format is allowed, but logs a warning because
+/// values outside `[0, 1]` are clamped.
+pub(crate) fn check_export_format(
+    format: ExportFormat,
+    encoder: &EncoderSettings,
+    texture_format: TextureFormat,
+) -> Result<(), ImageStorageError> {
+    if !format.is_enabled() {
+        return Err(ImageStorageError::FormatDisabled(format));
+    }
+
+    if is_hdr_texture(texture_format)? && !encoder.keeps_hdr(format) {
+        warn!(
+            "saving {:?} texture as {:?}, which clamps values outside [0, 1]",
+            texture_format, format
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks that frames of a source texture with the given format can be saved as an animation in
+/// `format`, which always has 8 bits per channel.
+pub(crate) fn check_animation_format(
+    format: AnimationFormat,
+    texture_format: TextureFormat,
+) -> Result<(), ImageStorageError> {
+    if !format.is_enabled() {
+        return Err(ImageStorageError::AnimationFormatDisabled(format));
+    }
+
+    if is_hdr_texture(texture_format)? {
+        warn!(
+            "saving {:?} texture as {:?} animation, which clamps values outside [0, 1]",
+            texture_format, format
+        );
+    }
+
+    Ok(())
+}
+
+/// Checks if frames with the given texture format hold values outside `[0, 1]`, or returns an
+/// error if they can't be decoded at all.
+fn is_hdr_texture(texture_format: TextureFormat) -> Result<bool, ImageStorageError> {
+    use TextureFormat::*;
+    match texture_format {
+        Rgba8Unorm | Rgba8UnormSrgb | Bgra8Unorm | Bgra8UnormSrgb | Rgba16Unorm | Rgb10a2Unorm
+        | R8Unorm | R16Unorm | Depth16Unorm => Ok(false),
+        Rgba16Float | Rgba32Float | Rg11b10Ufloat | R16Float | R32Float | Depth32Float => Ok(true),
+        texture_format => Err(ImageStorageError::UnsupportedTextureFormat(texture_format)),
+    }
+}
+
+/// Interprets the unpadded texel bytes of a texture with the given format as an image.
+///
+/// Channels are reordered to RGBA where necessary. Single-channel formats are expanded to
+/// grayscale images, and so are depth formats, whose depth aspect is copied on its own.
+fn decode_texels(
+    image_bytes: Vec<u8>,
+    format: TextureFormat,
     width: u32,
     height: u32,
-    path: &str,
-) -> Result<(), ImageStorageError>
-where
-    P::Subpixel: AnyBitPattern,
-    [P::Subpixel]: EncodableLayout,
-{
-    let Some(buffer) = ImageBuffer::<P, _>::from_raw(width, height, image_bytes) else {
-        return Err(ImageStorageError::BufferCreation);
+) -> Result<DynamicImage, ImageStorageError> {
+    let image = match format {
+        TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb => {
+            RgbaImage::from_raw(width, height, image_bytes).map(DynamicImage::ImageRgba8)
+        }
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            let mut image_bytes = image_bytes;
+            for texel in image_bytes.chunks_exact_mut(4) {
+                texel.swap(0, 2);
+            }
+
+            RgbaImage::from_raw(width, height, image_bytes).map(DynamicImage::ImageRgba8)
+        }
+        TextureFormat::Rgba16Unorm => {
+            ImageBuffer::from_raw(width, height, decode_u16(&image_bytes).collect())
+                .map(DynamicImage::ImageRgba16)
+        }
+        TextureFormat::Rgb10a2Unorm => {
+            let expand = |value: u32, bits: u32| {
+                (value & ((1 << bits) - 1)) * u16::MAX as u32 / ((1 << bits) - 1)
+            };
+            let pixels = decode_u32(&image_bytes)
+                .flat_map(|texel| {
+                    [
+                        expand(texel, 10),
+                        expand(texel >> 10, 10),
+                        expand(texel >> 20, 10),
+                        expand(texel >> 30, 2),
+                    ]
+                })
+                .map(|value| value as u16)
+                .collect();
+
+            ImageBuffer::from_raw(width, height, pixels).map(DynamicImage::ImageRgba16)
+        }
+        TextureFormat::Rgba16Float => {
+            Rgba32FImage::from_raw(width, height, decode_f16(&image_bytes).collect())
+                .map(DynamicImage::ImageRgba32F)
+        }
+        TextureFormat::Rgba32Float => {
+            Rgba32FImage::from_raw(width, height, decode_f32(&image_bytes).collect())
+                .map(DynamicImage::ImageRgba32F)
+        }
+        TextureFormat::Rg11b10Ufloat => {
+            // The packed channels share the exponent layout of half floats, so their mantissas
+            // only need to be shifted into place.
+            let expand = |bits: u32| f16::from_bits(bits as u16).to_f32();
+            let pixels = decode_u32(&image_bytes)
+                .flat_map(|texel| {
+                    [
+                        expand((texel & 0x7ff) << 4),
+                        expand(((texel >> 11) & 0x7ff) << 4),
+                        expand(((texel >> 22) & 0x3ff) << 5),
+                        1.0,
+                    ]
+                })
+                .collect();
+
+            Rgba32FImage::from_raw(width, height, pixels).map(DynamicImage::ImageRgba32F)
+        }
+        TextureFormat::R8Unorm => {
+            GrayImage::from_raw(width, height, image_bytes).map(DynamicImage::ImageLuma8)
+        }
+        TextureFormat::R16Unorm | TextureFormat::Depth16Unorm => {
+            ImageBuffer::from_raw(width, height, decode_u16(&image_bytes).collect())
+                .map(DynamicImage::ImageLuma16)
+        }
+        TextureFormat::R16Float => Rgba32FImage::from_raw(
+            width,
+            height,
+            decode_f16(&image_bytes)
+                .flat_map(|value| [value, value, value, 1.0])
+                .collect(),
+        )
+        .map(DynamicImage::ImageRgba32F),
+        TextureFormat::R32Float | TextureFormat::Depth32Float => Rgba32FImage::from_raw(
+            width,
+            height,
+            decode_f32(&image_bytes)
+                .flat_map(|value| [value, value, value, 1.0])
+                .collect(),
+        )
+        .map(DynamicImage::ImageRgba32F),
+        format => return Err(ImageStorageError::UnsupportedTextureFormat(format)),
     };
 
-    buffer.save(path)?;
+    image.ok_or(ImageStorageError::BufferCreation)
+}
 
-    Ok(())
+fn decode_u16(bytes: &[u8]) -> impl Iterator<Item = u16> + '_ {
+    bytes
+        .chunks_exact(2)
+        .map(|bytes| u16::from_le_bytes([bytes[0], bytes[1]]))
+}
+
+fn decode_u32(bytes: &[u8]) -> impl Iterator<Item = u32> + '_ {
+    bytes
+        .chunks_exact(4)
+        .map(|bytes| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+fn decode_f16(bytes: &[u8]) -> impl Iterator<Item = f32> + '_ {
+    decode_u16(bytes).map(|bits| f16::from_bits(bits).to_f32())
+}
+
+fn decode_f32(bytes: &[u8]) -> impl Iterator<Item = f32> + '_ {
+    decode_u32(bytes).map(f32::from_bits)
+}
+
+/// Quantizes an image to 8 bits per channel.
+///
+/// Float images are assumed to hold linear color values, which are clamped to `[0, 1]` and
+/// encoded with the sRGB transfer function, matching what `Rgba8UnormSrgb` targets contain.
+pub(crate) fn into_rgba8(image: DynamicImage) -> RgbaImage {
+    match image {
+        DynamicImage::ImageRgba32F(image) => {
+            let quantize = |value: f32| (value.clamp(0.0, 1.0) * 255.0).round() as u8;
+            let pixels = image
+                .pixels()
+                .flat_map(|&image::Rgba([r, g, b, a])| {
+                    [
+                        quantize(linear_to_srgb(r)),
+                        quantize(linear_to_srgb(g)),
+                        quantize(linear_to_srgb(b)),
+                        quantize(a),
+                    ]
+                })
+                .collect();
+
+            RgbaImage::from_raw(image.width(), image.height(), pixels).unwrap()
+        }
+        image => image.into_rgba8(),
+    }
+}
+
+/// Quantizes an image to 16 bits per channel.
+///
+/// Like in [`into_rgba8`], float images are encoded with the sRGB transfer function. Images with
+/// less precision are expanded.
+#[cfg(any(feature = "png", feature = "tiff"))]
+fn into_rgba16(image: DynamicImage) -> ImageBuffer<image::Rgba<u16>, Vec<u16>> {
+    match image {
+        DynamicImage::ImageRgba32F(image) => {
+            let quantize = |value: f32| (value.clamp(0.0, 1.0) * 65535.0).round() as u16;
+            let pixels = image
+                .pixels()
+                .flat_map(|&image::Rgba([r, g, b, a])| {
+                    [
+                        quantize(linear_to_srgb(r)),
+                        quantize(linear_to_srgb(g)),
+                        quantize(linear_to_srgb(b)),
+                        quantize(a),
+                    ]
+                })
+                .collect();
+
+            ImageBuffer::from_raw(image.width(), image.height(), pixels).unwrap()
+        }
+        image => image.into_rgba16(),
+    }
+}
+
+/// Quantizes an image to the given bit depth.
+#[cfg(any(feature = "png", feature = "tiff"))]
+fn with_bit_depth(image: DynamicImage, bit_depth: BitDepth) -> DynamicImage {
+    match bit_depth {
+        BitDepth::Eight => DynamicImage::ImageRgba8(into_rgba8(image)),
+        BitDepth::Sixteen => DynamicImage::ImageRgba16(into_rgba16(image)),
+    }
+}
+
+/// Quantizes an image to 8 bits per channel like [`into_rgba8`], but keeps grayscale images
+/// single-channel and drops the alpha channel unless `alpha` is set.
+#[cfg(any(feature = "bmp", feature = "tga", feature = "pnm"))]
+fn into_8bit(image: DynamicImage, alpha: bool) -> DynamicImage {
+    match image {
+        DynamicImage::ImageLuma8(_) | DynamicImage::ImageLuma16(_) => {
+            DynamicImage::ImageLuma8(image.into_luma8())
+        }
+        image if alpha => DynamicImage::ImageRgba8(into_rgba8(image)),
+        image => DynamicImage::ImageRgb8(DynamicImage::ImageRgba8(into_rgba8(image)).into_rgb8()),
+    }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Re-encodes the color values of an image that was decoded from a texture with the given format.
+///
+/// The values are decoded to linear light first, using the sRGB transfer function for sRGB
+/// textures. The result is quantized to `bit_depth` straight from the re-encoded values, so that
+/// quantizing it again neither encodes the values again nor loses precision, e.g. when an 8-bit
+/// image is saved with 16 bits. Without a bit depth, the result keeps the precision of the image.
+pub(crate) fn encode_colors(
+    image: DynamicImage,
+    texture_format: TextureFormat,
+    transfer_function: TransferFunction,
+    bit_depth: Option<BitDepth>,
+) -> DynamicImage {
+    let decode: fn(f32) -> f32 = if texture_format.is_srgb() {
+        srgb_to_linear
+    } else {
+        |value| value
+    };
+
+    let color = image.color();
+    let mut pixels = image.into_rgba32f();
+    for pixel in pixels.pixels_mut() {
+        for value in &mut pixel.0[..3] {
+            *value = transfer_function.encode(decode(*value));
+        }
+    }
+
+    let image = DynamicImage::ImageRgba32F(pixels);
+    let luma = matches!(color, image::ColorType::L8 | image::ColorType::L16);
+    match bit_depth {
+        Some(BitDepth::Eight) if luma => DynamicImage::ImageLuma8(image.into_luma8()),
+        Some(BitDepth::Eight) => DynamicImage::ImageRgba8(image.into_rgba8()),
+        Some(BitDepth::Sixteen) if luma => DynamicImage::ImageLuma16(image.into_luma16()),
+        Some(BitDepth::Sixteen) => DynamicImage::ImageRgba16(image.into_rgba16()),
+        None => match color {
+            image::ColorType::L8 => DynamicImage::ImageLuma8(image.into_luma8()),
+            image::ColorType::L16 => DynamicImage::ImageLuma16(image.into_luma16()),
+            image::ColorType::Rgba8 => DynamicImage::ImageRgba8(image.into_rgba8()),
+            image::ColorType::Rgba32F => image,
+            _ => DynamicImage::ImageRgba16(image.into_rgba16()),
+        },
+    }
+}
+
+/// Decodes a frame, scaling it down to fit within `max_size`.
+pub(crate) fn decode_frame(
+    frame: ExportedFrame,
+    max_size: Option<UVec2>,
+) -> Result<DynamicImage, ImageStorageError> {
+    let image = decode_texels(frame.image_bytes, frame.format, frame.width, frame.height)?;
+    Ok(match max_size {
+        Some(max_size) if image.width() > max_size.x || image.height() > max_size.y => {
+            image.resize(max_size.x, max_size.y, FilterType::Triangle)
+        }
+        _ => image,
+    })
+}
+
+/// Saves a frame as an image file.
+///
+/// Formats that support them keep the 16-bit and float channels of the frame, others are quantized
+/// to 8 bits per channel. If a transfer function is given, the color values are encoded with it
+/// first.
+#[cfg_attr(
+    not(any(feature = "png", feature = "jpeg", feature = "exr")),
+    allow(unused_variables)
+)]
 pub fn save_image(
-    output_dir: &str,
-    extension: &str,
-    mut image_bytes: Vec<u8>,
-    bytes_per_row: usize,
-    padded_bytes_per_row: usize,
-    width: u32,
-    height: u32,
-    frame_id: u64,
+    path: &Path,
+    format: ExportFormat,
+    encoder: &EncoderSettings,
+    max_size: Option<UVec2>,
+    transfer_function: Option<TransferFunction>,
+    frame: ExportedFrame,
 ) -> Result<(), ImageStorageError> {
-    create_dir_all(output_dir)?;
+    if let Some(parent) = path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let texture_format = frame.format;
+    let image = decode_frame(frame, max_size)?;
+    let image = match transfer_function {
+        Some(transfer_function) => encode_colors(
+            image,
+            texture_format,
+            transfer_function,
+            encoder.bit_depth(format),
+        ),
+        None => image,
+    };
+
+    match format {
+        #[cfg(feature = "png")]
+        ExportFormat::Png => write_png(
+            path,
+            &with_bit_depth(image, encoder.png.bit_depth),
+            &encoder.png,
+            transfer_function,
+        ),
+        #[cfg(feature = "jpeg")]
+        ExportFormat::Jpeg => write_jpeg(
+            path,
+            &DynamicImage::ImageRgba8(into_rgba8(image)).into_rgb8(),
+            &encoder.jpeg,
+        ),
+        #[cfg(feature = "exr")]
+        ExportFormat::Exr => write_exr(path, &image.into_rgba32f(), &encoder.exr),
+        #[cfg(feature = "tiff")]
+        ExportFormat::Tiff => write_tiff(
+            path,
+            &match encoder.tiff.bit_depth {
+                Some(bit_depth) => with_bit_depth(image, bit_depth),
+                None => image,
+            },
+        ),
+        #[cfg(feature = "qoi")]
+        ExportFormat::Qoi => write_qoi(path, &into_rgba8(image)),
+        #[cfg(feature = "bmp")]
+        ExportFormat::Bmp => write_bmp(path, &into_8bit(image, true)),
+        #[cfg(feature = "tga")]
+        ExportFormat::Tga => write_tga(path, &into_8bit(image, true)),
+        #[cfg(feature = "pnm")]
+        ExportFormat::Pnm => write_pnm(path, &into_8bit(image, false)),
+        #[cfg(feature = "hdr")]
+        ExportFormat::Hdr => write_hdr(path, &image.into_rgb32f()),
+        #[allow(unreachable_patterns)]
+        format => Err(ImageStorageError::FormatDisabled(format)),
+    }
+}
+
+/// Checks that a file name template only has the placeholders documented on
+/// [`ImageExportSettings::file_name`].
+pub(crate) fn check_file_name(template: &str) -> Result<(), ImageStorageError> {
+    format_file_name(template, 0, "", Entity::PLACEHOLDER, 0, "").map(drop)
+}
+
+/// Fills in the placeholders of a file name template as documented on
+/// [`ImageExportSettings::file_name`].
+fn format_file_name(
+    template: &str,
+    frame_number: u64,
+    name: &str,
+    entity: Entity,
+    timestamp: u64,
+    extension: &str,
+) -> Result<String, ImageStorageError> {
+    let invalid = || ImageStorageError::InvalidFileName(template.into());
+
+    let mut file_name = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        file_name.push_str(&rest[..start]);
+        rest = &rest[start + 1..];
 
-    if bytes_per_row != padded_bytes_per_row {
-        let mut unpadded_bytes = Vec::<u8>::with_capacity(height as usize * bytes_per_row);
+        let end = rest.find('}').ok_or_else(invalid)?;
+        let (key, format) = rest[..end].split_once(':').unwrap_or((&rest[..end], ""));
+        rest = &rest[end + 1..];
 
-        for padded_row in image_bytes.chunks(padded_bytes_per_row) {
-            unpadded_bytes.extend_from_slice(&padded_row[..bytes_per_row]);
+        match (key, format) {
+            ("frame", "") => file_name.push_str(&frame_number.to_string()),
+            ("frame", width) if width.starts_with('0') => {
+                let width = width.parse::<usize>().map_err(|_| invalid())?;
+                file_name.push_str(&format!("{:0width$}", frame_number));
+            }
+            ("name", "") => file_name.push_str(name),
+            ("entity", "") => file_name.push_str(&entity.to_string()),
+            ("timestamp", "") => file_name.push_str(&timestamp.to_string()),
+            ("ext", "") => file_name.push_str(extension),
+            _ => return Err(invalid()),
         }
+    }
+    file_name.push_str(rest);
+
+    Ok(file_name)
+}
 
-        image_bytes = unpadded_bytes;
+/// Saves frames as image files according to [`ImageExportSettings`].
+pub(crate) struct FileSink {
+    pub(crate) settings: ImageExportSettings,
+    /// The time the export started, in seconds since the Unix epoch.
+    pub(crate) timestamp: u64,
+    pub(crate) name: String,
+}
+
+impl FileSink {
+    /// Returns the path that `frame` is saved to.
+    fn path(&self, frame: &ExportedFrame) -> Result<PathBuf, ImageStorageError> {
+        let file_name = format_file_name(
+            &self.settings.file_name,
+            frame.frame_id + self.settings.frame_offset,
+            &self.name,
+            frame.entity,
+            self.timestamp,
+            self.settings.format.extension(),
+        )?;
+
+        Ok(Path::new(&self.settings.output_dir).join(file_name))
+    }
+}
+
+impl ExportSink for FileSink {
+    fn write_frame(&self, frame: ExportedFrame, export_threads: &ExportThreads) {
+        let path = match self.path(&frame) {
+            Ok(path) => path,
+            Err(err) => {
+                error!({ error = %err }, "failed saving image to disk");
+                return;
+            }
+        };
+
+        let format = self.settings.format;
+        let encoder = self.settings.encoder.clone();
+        let max_size = self.settings.max_size;
+        let transfer_function = self.settings.transfer_function;
+        export_threads.execute(move || {
+            if let Err(err) =
+                save_image(&path, format, &encoder, max_size, transfer_function, frame)
+            {
+                error!({ error = %err }, "failed saving image to disk");
+            }
+        });
     }
+}
 
-    let path = format!("{}/{:05}.{}", output_dir, frame_id, extension);
+/// Collects a frame of an exporter and the frames of its passes, and saves them as the layers of
+/// one EXR file once all of them have been read back.
+pub(crate) struct LayeredFile {
+    sink: FileSink,
+    /// The name of each layer, or `None` for the frame of the exporter.
+    names: Vec<Option<String>>,
+    frames: Mutex<Vec<Option<ExportedFrame>>>,
+}
 
-    match extension {
-        "exr" => {
-            save_buffer::<Rgba<f32>>(
-                bytemuck::cast_slice(&image_bytes),
-                width,
-                height,
-                path.as_str(),
-            )?;
+impl LayeredFile {
+    pub(crate) fn new(sink: FileSink, names: Vec<Option<String>>) -> Self {
+        Self {
+            sink,
+            frames: Mutex::new(vec![None; names.len()]),
+            names,
         }
-        _ => {
-            save_buffer::<Rgba<u8>>(&image_bytes, width, height, path.as_str())?;
+    }
+
+    #[cfg_attr(not(feature = "exr"), allow(unused_variables))]
+    fn save(&self, frames: Vec<ExportedFrame>) -> Result<(), ImageStorageError> {
+        let path = self.sink.path(&frames[0])?;
+        if let Some(parent) = path.parent() {
+            create_dir_all(parent)?;
         }
+
+        // Like separate passes, only the layer of the exporter is encoded with its transfer
+        // function.
+        let max_size = self.sink.settings.max_size;
+        let layers = self
+            .names
+            .iter()
+            .zip(frames)
+            .map(|(name, frame)| {
+                let texture_format = frame.format;
+                let mut image = decode_frame(frame, max_size)?;
+                if let (None, Some(transfer_function)) =
+                    (name, self.sink.settings.transfer_function)
+                {
+                    image = encode_colors(image, texture_format, transfer_function, None);
+                }
+
+                Ok((name.as_deref(), image.into_rgba32f()))
+            })
+            .collect::<Result<Vec<_>, ImageStorageError>>()?;
+
+        if let Some((name, _)) = layers
+            .iter()
+            .find(|(_, image)| image.dimensions() != layers[0].1.dimensions())
+        {
+            return Err(ImageStorageError::LayerSizeMismatch(
+                name.unwrap_or_default().into(),
+            ));
+        }
+
+        #[cfg(feature = "exr")]
+        return write_exr_layers(&path, &layers, &self.sink.settings.encoder.exr);
+        #[cfg(not(feature = "exr"))]
+        return Err(ImageStorageError::FormatDisabled(ExportFormat::Exr));
     }
+}
 
-    Ok(())
+impl Drop for LayeredFile {
+    fn drop(&mut self) {
+        // Files are only left incomplete if some of their layers weren't read back, e.g. because
+        // mapping their buffer failed. Every file is reported once.
+        let frames = self.frames.get_mut().unwrap_or_else(|err| err.into_inner());
+        if let Some(frame) = frames.iter().flatten().next() {
+            error!(
+                { entity = %frame.entity, frame_id = frame.frame_id },
+                "failed saving layered image to disk, some of its layers were never read back"
+            );
+        }
+    }
+}
+
+/// Receives one layer of a [`LayeredFile`].
+pub(crate) struct LayerSink {
+    pub(crate) file: Arc<LayeredFile>,
+    pub(crate) index: usize,
+}
+
+impl ExportSink for LayerSink {
+    fn write_frame(&self, frame: ExportedFrame, export_threads: &ExportThreads) {
+        let mut frames = self.file.frames.lock().unwrap();
+        frames[self.index] = Some(frame);
+        if frames.iter().any(Option::is_none) {
+            return;
+        }
+
+        let frames = frames.drain(..).flatten().collect();
+        let file = self.file.clone();
+        export_threads.execute(move || {
+            if let Err(err) = file.save(frames) {
+                error!({ error = %err }, "failed saving image to disk");
+            }
+        });
+    }
 }
@@ -0,0 +1,171 @@
+use bevy::{log::warn, prelude::Resource};
+use crossbeam_channel::{Receiver, Sender, TrySendError};
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, OnceLock,
+    },
+};
+
+type Job = Box<dyn FnOnce() + Send>;
+
+/// What happens to a frame when the export queue is full.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum QueueFullBehavior {
+    /// Blocks the app until a worker is available, so that no frame is lost. Use this for
+    /// offline rendering.
+    #[default]
+    Block,
+    /// Drops the frame with a warning, so that the app keeps running at full speed. Use this for
+    /// live capture.
+    DropFrame,
+}
+
+/// Configures the worker pool that encodes and saves frames.
+#[derive(Clone, Debug)]
+pub struct ExportThreadSettings {
+    /// The number of worker threads. Defaults to the available parallelism.
+    pub worker_count: usize,
+    /// The number of frames that can wait for a worker, or for the thread of an
+    /// [`ImageExportPipe`](crate::ImageExportPipe) or
+    /// [`ImageExportAnimation`](crate::ImageExportAnimation), before [`on_full`](Self::on_full)
+    /// takes effect.
+    pub queue_capacity: usize,
+    pub on_full: QueueFullBehavior,
+}
+
+impl Default for ExportThreadSettings {
+    fn default() -> Self {
+        let worker_count = std::thread::available_parallelism()
+            .map(NonZeroUsize::get)
+            .unwrap_or(4);
+
+        Self {
+            worker_count,
+            queue_capacity: 2 * worker_count,
+            on_full: QueueFullBehavior::default(),
+        }
+    }
+}
+
+#[derive(Default)]
+struct WorkerPool {
+    settings: ExportThreadSettings,
+    sender: OnceLock<Sender<Job>>,
+}
+
+impl WorkerPool {
+    /// Returns the sender of the job queue, starting the workers on first use.
+    fn sender(&self, count: &Arc<AtomicUsize>) -> &Sender<Job> {
+        self.sender.get_or_init(|| {
+            let (sender, receiver) =
+                crossbeam_channel::bounded::<Job>(self.settings.queue_capacity);
+
+            for _ in 0..self.settings.worker_count.max(1) {
+                let receiver = receiver.clone();
+                let count = count.clone();
+
+                std::thread::spawn(move || {
+                    for job in receiver {
+                        job();
+                        count.fetch_sub(1, Ordering::SeqCst);
+                    }
+                });
+            }
+
+            sender
+        })
+    }
+}
+
+/// Runs the work of saving frames in the background and keeps track of it.
+#[derive(Default, Clone, Resource)]
+pub struct ExportThreads {
+    count: Arc<AtomicUsize>,
+    pool: Arc<WorkerPool>,
+}
+
+impl ExportThreads {
+    pub fn new(settings: ExportThreadSettings) -> Self {
+        Self {
+            count: Arc::default(),
+            pool: Arc::new(WorkerPool {
+                settings,
+                sender: OnceLock::new(),
+            }),
+        }
+    }
+
+    /// Returns the number of jobs and threads currently queued or running.
+    pub fn thread_count(&self) -> usize {
+        self.count.load(Ordering::SeqCst)
+    }
+
+    /// Checks if all threads have finished.
+    pub fn is_finished(&self) -> bool {
+        self.thread_count() == 0
+    }
+
+    /// Blocks the main thread until all frames have been saved successfully.
+    pub fn finish(&self) {
+        while !self.is_finished() {
+            std::thread::sleep(std::time::Duration::from_secs_f32(0.25));
+        }
+    }
+
+    /// Runs `job` on the worker pool.
+    ///
+    /// If the queue is full, this either blocks or drops the job, depending on
+    /// [`ExportThreadSettings::on_full`]. Returns `false` if the job was dropped.
+    pub fn execute(&self, job: impl FnOnce() + Send + 'static) -> bool {
+        let sender = self.pool.sender(&self.count);
+        let job: Job = Box::new(job);
+
+        self.count.fetch_add(1, Ordering::SeqCst);
+        let sent = self.send(sender, job);
+
+        if !sent {
+            self.count.fetch_sub(1, Ordering::SeqCst);
+        }
+
+        sent
+    }
+
+    /// Creates a channel for frames that a thread started with [`spawn`](Self::spawn) works
+    /// through, which holds as many frames as the queue of the pool.
+    pub(crate) fn bounded<T>(&self) -> (Sender<T>, Receiver<T>) {
+        crossbeam_channel::bounded(self.pool.settings.queue_capacity)
+    }
+
+    /// Sends `value` to a channel created with [`bounded`](Self::bounded).
+    ///
+    /// If the channel is full, this either blocks or drops the value, depending on
+    /// [`ExportThreadSettings::on_full`]. Returns `false` if the value was dropped.
+    pub(crate) fn send<T>(&self, sender: &Sender<T>, value: T) -> bool {
+        let result = match self.pool.settings.on_full {
+            QueueFullBehavior::Block => sender.send(value).map_err(|_| ()),
+            QueueFullBehavior::DropFrame => sender.try_send(value).map_err(|err| {
+                if let TrySendError::Full(_) = err {
+                    warn!("export queue is full, dropping frame");
+                }
+            }),
+        };
+
+        result.is_ok()
+    }
+
+    /// Runs `f` on a dedicated thread that [`ExportThreads::finish`] waits for.
+    ///
+    /// Use this for long-running work such as feeding an external process, which would otherwise
+    /// occupy a worker of the pool.
+    pub fn spawn(&self, f: impl FnOnce() + Send + 'static) {
+        let count = self.count.clone();
+
+        count.fetch_add(1, Ordering::SeqCst);
+        std::thread::spawn(move || {
+            f();
+            count.fetch_sub(1, Ordering::SeqCst);
+        });
+    }
+}
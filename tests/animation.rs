@@ -0,0 +1,148 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    AnimationFormat, ImageExport, ImageExportAnimation, ImageExportFinished, ImageExportPlugin,
+    ImageExportRange, ImageExportSource,
+};
+use image::{
+    codecs::{gif::GifDecoder, png::PngDecoder, webp::WebPDecoder},
+    AnimationDecoder,
+};
+use std::{fs::File, io::BufReader};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u64 = 5;
+
+const FRAME_RATE: u32 = 30;
+
+/// Gives up if the exporters haven't finished after this many frames.
+const MAX_FRAMES: u32 = 60;
+
+const ANIMATIONS: [(&str, AnimationFormat); 3] = [
+    ("out/animation/clip.gif", AnimationFormat::Gif),
+    ("out/animation/clip.png", AnimationFormat::Apng),
+    ("out/animation/clip.webp", AnimationFormat::WebP),
+];
+
+#[test]
+fn test_animation() -> anyhow::Result<()> {
+    for (path, _) in ANIMATIONS {
+        let _ = std::fs::remove_file(path);
+    }
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    // The app is dropped right away, so that the last frames of the animations are read back.
+    let exit = test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    if exit != AppExit::Success {
+        anyhow::bail!("the exporters didn't finish after {} frames", MAX_FRAMES);
+    }
+
+    export_threads.finish();
+
+    for (path, format) in ANIMATIONS {
+        let file = BufReader::new(File::open(path)?);
+        let frames = match format {
+            AnimationFormat::Gif => GifDecoder::new(file)?.into_frames(),
+            AnimationFormat::Apng => PngDecoder::new(file)?.apng()?.into_frames(),
+            AnimationFormat::WebP => WebPDecoder::new(file)?.into_frames(),
+        }
+        .collect_frames()?;
+
+        if frames.len() != FRAME_COUNT as usize {
+            anyhow::bail!(
+                "expected {} frames in {}, got {}",
+                FRAME_COUNT,
+                path,
+                frames.len()
+            );
+        }
+
+        if let Some(frame) = frames
+            .iter()
+            .find(|frame| frame.buffer().dimensions() != (WIDTH, HEIGHT))
+        {
+            anyhow::bail!(
+                "expected frames of {}x{} in {}, got {:?}",
+                WIDTH,
+                HEIGHT,
+                path,
+                frame.buffer().dimensions()
+            );
+        }
+
+        // WebP durations are whole milliseconds, whose rounding errors mustn't add up.
+        if format == AnimationFormat::WebP {
+            let duration = frames
+                .iter()
+                .map(|frame| {
+                    let (numer, denom) = frame.delay().numer_denom_ms();
+                    numer as f64 / denom as f64
+                })
+                .sum::<f64>();
+            let expected = (FRAME_COUNT as f64 * 1000.0 / FRAME_RATE as f64).round();
+            if duration != expected {
+                anyhow::bail!(
+                    "expected a duration of {} ms in {}, got {}",
+                    expected,
+                    path,
+                    duration
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    let export_source = export_sources.add(output_texture_handle);
+    for (path, format) in ANIMATIONS {
+        commands.spawn((
+            ImageExport(export_source.clone()),
+            ImageExportAnimation::new(path, format, FRAME_RATE),
+            ImageExportRange::new(1..=FRAME_COUNT),
+        ));
+    }
+}
+
+fn update(
+    mut app_exit_events: MessageWriter<AppExit>,
+    mut finished: MessageReader<ImageExportFinished>,
+    mut finished_count: Local<usize>,
+    mut frame: Local<u32>,
+) {
+    *frame += 1;
+    *finished_count += finished.read().count();
+
+    if *finished_count >= ANIMATIONS.len() {
+        app_exit_events.write(AppExit::Success);
+    } else if *frame >= MAX_FRAMES {
+        app_exit_events.write(AppExit::error());
+    }
+}
@@ -1,48 +1,16 @@
+mod common;
+
+use crate::common::{render_target, test_app};
 use anyhow::anyhow;
-use bevy::{
-    app::plugin_group,
-    camera::RenderTarget,
-    prelude::*,
-    render::{
-        render_resource::{
-            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
-        },
-        RenderPlugin,
-    },
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ImageExport, ImageExportPlugin, ImageExportSource, ImageExportStart, ImageExportSystems,
 };
-use bevy_image_export::{ImageExport, ImageExportPlugin, ImageExportSource};
 use std::f32::consts::PI;
 
 const WIDTH: u32 = 16;
 const HEIGHT: u32 = 16;
 
-/// Number of frames to wait before starting the export. This is necessary because Bevy doesn't
-/// predictably start rendering on the first frame.
-const STARTUP_FRAMES: u32 = 2;
-
-plugin_group! {
-    pub struct TestPlugins {
-        bevy::app:::PanicHandlerPlugin,
-        bevy::log:::LogPlugin,
-        bevy::app:::TaskPoolPlugin,
-        bevy::diagnostic:::FrameCountPlugin,
-        bevy::time:::TimePlugin,
-        bevy::transform:::TransformPlugin,
-        bevy::app:::ScheduleRunnerPlugin,
-        bevy::window:::WindowPlugin,
-        bevy::asset:::AssetPlugin,
-        bevy::render:::RenderPlugin,
-        bevy::image:::ImagePlugin,
-        bevy::mesh:::MeshPlugin,
-        bevy::camera:::CameraPlugin,
-        bevy::light:::LightPlugin,
-        bevy::render::pipelined_rendering:::PipelinedRenderingPlugin,
-        bevy::core_pipeline:::CorePipelinePlugin,
-        bevy::post_process:::PostProcessPlugin,
-        bevy::pbr:::PbrPlugin,
-    }
-}
-
 fn open_image(path: &str) -> anyhow::Result<Vec<u8>> {
     Ok(image::open(path)
         .map_err(|e| anyhow::anyhow!("failed to open {}: {}", path, e))?
@@ -76,29 +44,21 @@ fn test_basic() -> anyhow::Result<()> {
     let export_threads = export_plugin.threads.clone();
     let image_count = 5;
 
-    App::new()
-        .add_plugins((
-            TestPlugins
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        resolution: (WIDTH, HEIGHT).into(),
-                        ..default()
-                    }),
-                    ..default()
-                })
-                .set(RenderPlugin {
-                    synchronous_pipeline_compilation: true,
-                    ..Default::default()
-                }),
-            export_plugin,
-        ))
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
         .insert_resource(GlobalAmbientLight {
             color: Color::WHITE,
             brightness: 1000.0,
             affects_lightmapped_meshes: true,
         })
         .insert_resource(ImageCount(image_count))
-        .add_systems(Update, (setup, update).chain())
+        .add_systems(Startup, setup)
+        .add_systems(
+            PostUpdate,
+            update
+                .after(ImageExportSystems::ImageExportSetup)
+                .before(TransformSystems::Propagate),
+        )
         .run();
 
     export_threads.finish();
@@ -121,39 +81,12 @@ fn setup(
     mut materials: ResMut<Assets<StandardMaterial>>,
     mut images: ResMut<Assets<Image>>,
     mut export_sources: ResMut<Assets<ImageExportSource>>,
-    mut frame: Local<u32>,
 ) {
-    *frame += 1;
-    let frame = *frame as i32 - STARTUP_FRAMES as i32;
-    if frame != 1 {
-        return;
-    }
-
-    let output_texture_handle = {
-        let size = Extent3d {
-            width: WIDTH,
-            height: HEIGHT,
-            ..default()
-        };
-        let mut export_texture = Image {
-            texture_descriptor: TextureDescriptor {
-                label: None,
-                size,
-                dimension: TextureDimension::D2,
-                format: TextureFormat::Rgba8UnormSrgb,
-                mip_level_count: 1,
-                sample_count: 1,
-                usage: TextureUsages::COPY_DST
-                    | TextureUsages::COPY_SRC
-                    | TextureUsages::RENDER_ATTACHMENT,
-                view_formats: &[],
-            },
-            ..default()
-        };
-        export_texture.resize(size);
-
-        images.add(export_texture)
-    };
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
 
     commands.spawn((
         Camera3d::default(),
@@ -179,24 +112,24 @@ fn setup(
 
 #[derive(Component)]
 struct Moving;
+/// Moves the cube for the frame that is exported in the current update.
 fn update(
     image_count: Res<ImageCount>,
     mut app_exit_events: MessageWriter<AppExit>,
-    mut frame: Local<u32>,
+    exporters: Query<&ImageExportStart>,
     mut transforms: Query<&mut Transform, With<Moving>>,
 ) {
-    *frame += 1;
-    let frame = *frame as i32 - STARTUP_FRAMES as i32;
-    if frame < 1 {
+    let Ok(start) = exporters.single() else {
         return;
-    }
+    };
 
+    let frame = start.frame_id();
     let theta = (frame - 1) as f32 * 0.25 * PI;
     for mut transform in &mut transforms {
         transform.translation = Vec3::new(theta.sin(), theta.cos(), 0.0);
     }
 
-    if frame >= (image_count.0 as i32) {
+    if frame >= image_count.0 as u64 {
         app_exit_events.write(AppExit::Success);
     }
 }
@@ -0,0 +1,133 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{
+    camera::RenderTarget, core_pipeline::tonemapping::Tonemapping, prelude::*,
+    render::render_resource::TextureFormat,
+};
+use bevy_image_export::{
+    BitDepth, EncoderSettings, ExportFormat, ImageExport, ImageExportPlugin, ImageExportSettings,
+    ImageExportSource,
+};
+use image::ColorType;
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/bit_depth";
+
+/// The formats that an `Rgba16Float` texture is saved in with 16 bits per channel.
+const FORMATS: [ExportFormat; 2] = [ExportFormat::Png, ExportFormat::Tiff];
+
+/// The sRGB value that the texture is cleared to, which the files should contain again.
+const CLEAR_VALUE: f32 = 0.5;
+
+#[test]
+fn test_bit_depth() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    for format in FORMATS {
+        let dir = output_dir(format);
+        let mut paths = std::fs::read_dir(&dir)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        paths.sort();
+
+        if paths.is_empty() {
+            anyhow::bail!("expected {:?} frames in {}", format, dir);
+        }
+
+        for path in paths {
+            if path.extension().and_then(|ext| ext.to_str()) != Some(format.extension()) {
+                anyhow::bail!("expected {:?} files, got {}", format, path.display());
+            }
+
+            let image = image::open(&path)
+                .map_err(|e| anyhow::anyhow!("failed to open {}: {}", path.display(), e))?;
+            if image.color() != ColorType::Rgba16 {
+                anyhow::bail!(
+                    "expected 16-bit pixels in {}, got {:?}",
+                    path.display(),
+                    image.color()
+                );
+            }
+
+            let red = image.to_rgba16().get_pixel(0, 0)[0] as f32 / u16::MAX as f32;
+            if (red - CLEAR_VALUE).abs() > 0.02 {
+                anyhow::bail!(
+                    "expected sRGB encoded values in {}, got {}",
+                    path.display(),
+                    red
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn output_dir(format: ExportFormat) -> String {
+    format!("{}/{}", OUTPUT_DIR, format.extension())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba16Float,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+        Camera {
+            clear_color: ClearColorConfig::Custom(Color::srgb(
+                CLEAR_VALUE,
+                CLEAR_VALUE,
+                CLEAR_VALUE,
+            )),
+            ..default()
+        },
+        Tonemapping::None,
+    ));
+
+    let export_source = export_sources.add(output_texture_handle);
+    for format in FORMATS {
+        let mut encoder = EncoderSettings::default();
+        encoder.png.bit_depth = BitDepth::Sixteen;
+        encoder.tiff.bit_depth = Some(BitDepth::Sixteen);
+
+        commands.spawn((
+            ImageExport(export_source.clone()),
+            ImageExportSettings {
+                output_dir: output_dir(format),
+                format,
+                encoder,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,101 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ImageExport, ImageExportCapture, ImageExportPlugin, ImageExportRange, ImageExportSource,
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+/// Number of frames to run, which leaves the exporter idle after the captures.
+const FRAME_COUNT: u32 = 20;
+
+const OUTPUT_DIR: &str = "out/capture";
+
+#[test]
+fn test_capture() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    // Only the requested frames may be saved, even though the exporter kept running.
+    let files = std::fs::read_dir(OUTPUT_DIR)?.count();
+    if files != 2 {
+        anyhow::bail!("expected two captures, found {} files", files);
+    }
+
+    for file_name in ["thumbnail.png", "replaced.png"] {
+        let image = image::open(format!("{}/{}", OUTPUT_DIR, file_name))?;
+        if (image.width(), image.height()) != (WIDTH, HEIGHT) {
+            anyhow::bail!(
+                "unexpected size {}x{} of {}",
+                image.width(),
+                image.height(),
+                file_name
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    commands.spawn((
+        ImageExport(export_sources.add(output_texture_handle)),
+        ImageExportCapture::new(format!("{}/thumbnail.png", OUTPUT_DIR)),
+        // The capture stays pending through the skipped frames.
+        ImageExportRange::starting_at(3),
+    ));
+}
+
+fn update(
+    mut commands: Commands,
+    captures: Query<(Entity, &ImageExportCapture)>,
+    mut app_exit_events: MessageWriter<AppExit>,
+    mut frame: Local<u32>,
+    mut replaced: Local<bool>,
+) {
+    // A new component replaces the taken capture, which must not be mistaken for it.
+    for (entity, capture) in &captures {
+        if !*replaced && capture.pending().is_none() {
+            commands
+                .entity(entity)
+                .insert(ImageExportCapture::new(format!(
+                    "{}/replaced.png",
+                    OUTPUT_DIR
+                )));
+            *replaced = true;
+        }
+    }
+
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,113 @@
+// Each test only uses some of the helpers.
+#![allow(dead_code)]
+
+use bevy::{
+    app::plugin_group,
+    asset::RenderAssetUsages,
+    prelude::*,
+    render::{
+        render_resource::{
+            Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+        },
+        RenderPlugin,
+    },
+};
+
+plugin_group! {
+    pub struct TestPlugins {
+        bevy::app:::PanicHandlerPlugin,
+        bevy::log:::LogPlugin,
+        bevy::app:::TaskPoolPlugin,
+        bevy::diagnostic:::FrameCountPlugin,
+        bevy::time:::TimePlugin,
+        bevy::transform:::TransformPlugin,
+        bevy::app:::ScheduleRunnerPlugin,
+        bevy::window:::WindowPlugin,
+        bevy::asset:::AssetPlugin,
+        bevy::render:::RenderPlugin,
+        bevy::image:::ImagePlugin,
+        bevy::mesh:::MeshPlugin,
+        bevy::camera:::CameraPlugin,
+        bevy::light:::LightPlugin,
+        bevy::render::pipelined_rendering:::PipelinedRenderingPlugin,
+        bevy::core_pipeline:::CorePipelinePlugin,
+        bevy::post_process:::PostProcessPlugin,
+        bevy::pbr:::PbrPlugin,
+    }
+}
+
+/// Creates an app with the [`TestPlugins`] and a window of the given size, which compiles its
+/// pipelines before they are first used.
+pub fn test_app(width: u32, height: u32) -> App {
+    let mut app = App::new();
+    app.add_plugins(
+        TestPlugins
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    resolution: (width, height).into(),
+                    ..default()
+                }),
+                ..default()
+            })
+            .set(RenderPlugin {
+                synchronous_pipeline_compilation: true,
+                ..Default::default()
+            }),
+    );
+
+    app
+}
+
+/// Creates an image that cameras can render to and exporters can copy from.
+pub fn render_target(
+    images: &mut Assets<Image>,
+    format: TextureFormat,
+    size: UVec2,
+) -> Handle<Image> {
+    let size = Extent3d {
+        width: size.x,
+        height: size.y,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: None,
+            size,
+            dimension: TextureDimension::D2,
+            format,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::COPY_DST
+                | TextureUsages::COPY_SRC
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    image.resize(size);
+
+    images.add(image)
+}
+
+/// Creates an image that exporters can copy from, with every texel set to the given bytes.
+pub fn filled_image(
+    images: &mut Assets<Image>,
+    format: TextureFormat,
+    size: UVec2,
+    texel: &[u8],
+) -> Handle<Image> {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: size.x,
+            height: size.y,
+            ..default()
+        },
+        TextureDimension::D2,
+        texel,
+        format,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage |= TextureUsages::COPY_SRC;
+
+    images.add(image)
+}
@@ -0,0 +1,209 @@
+mod common;
+
+use crate::common::{filled_image, test_app};
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    EncoderSettings, ExportFormat, ExrCompression, ExrPrecision, ExrSettings, ImageExport,
+    ImageExportPlugin, ImageExportSettings, ImageExportSource, JpegSettings, StartBehavior,
+};
+use exr::{compression::Compression, meta::attribute::SampleType};
+use std::path::PathBuf;
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/encoder_settings";
+
+/// Values that 16-bit floats represent exactly.
+const VALUES: [f32; 4] = [0.25, 0.5, 2.0, 1.0];
+
+/// The EXR settings of the exporters, with the compression and sample type their files should
+/// have.
+const EXR_SETTINGS: [(&str, ExrCompression, ExrPrecision, Compression, SampleType); 2] = [
+    (
+        "half_zip",
+        ExrCompression::Zip,
+        ExrPrecision::Half,
+        Compression::ZIP16,
+        SampleType::F16,
+    ),
+    (
+        "full_uncompressed",
+        ExrCompression::Uncompressed,
+        ExrPrecision::Full,
+        Compression::Uncompressed,
+        SampleType::F32,
+    ),
+];
+
+/// The JPEG qualities of the exporters.
+const JPEG_QUALITIES: [u8; 2] = [100, 10];
+
+#[test]
+fn test_encoder_settings() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    // No camera renders to the images.
+    let export_plugin =
+        ImageExportPlugin::default().with_start_behavior(StartBehavior::WaitForSource);
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    for (dir, _, _, compression, sample_type) in EXR_SETTINGS {
+        let path = first_file(&output_dir(dir))?;
+
+        let meta = exr::meta::MetaData::read_from_file(&path, false)?;
+        let header = &meta.headers[0];
+        if header.compression != compression {
+            anyhow::bail!(
+                "expected {:?} compression in {}, got {:?}",
+                compression,
+                path.display(),
+                header.compression
+            );
+        }
+
+        if let Some(channel) = header
+            .channels
+            .list
+            .iter()
+            .find(|channel| channel.sample_type != sample_type)
+        {
+            anyhow::bail!(
+                "expected {:?} samples in {}, got {:?}",
+                sample_type,
+                path.display(),
+                channel.sample_type
+            );
+        }
+
+        // Values survive the round trip, whatever the compression.
+        let pixel = image::open(&path)?.into_rgba32f().get_pixel(0, 0).0;
+        if pixel != VALUES {
+            anyhow::bail!(
+                "expected {:?} in {}, got {:?}",
+                VALUES,
+                path.display(),
+                pixel
+            );
+        }
+    }
+
+    // Quality 100 leaves the DCT coefficients unquantized, lower qualities divide them.
+    for quality in JPEG_QUALITIES {
+        let path = first_file(&output_dir(&format!("jpeg_{}", quality)))?;
+        let bytes = std::fs::read(&path)?;
+        let table = quantization_table(&bytes).ok_or_else(|| {
+            anyhow::anyhow!("expected a quantization table in {}", path.display())
+        })?;
+
+        let unquantized = table.iter().all(|&value| value == 1);
+        if unquantized != (quality == 100) {
+            anyhow::bail!(
+                "unexpected quantization table for quality {}: {:?}",
+                quality,
+                table
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn output_dir(dir: &str) -> String {
+    format!("{}/{}", OUTPUT_DIR, dir)
+}
+
+/// Returns the path of the first file saved to `dir`.
+fn first_file(dir: &str) -> anyhow::Result<PathBuf> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    paths.sort();
+
+    paths
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("expected frames in {}", dir))
+}
+
+/// Returns the first 8-bit quantization table of a JPEG file.
+fn quantization_table(bytes: &[u8]) -> Option<&[u8]> {
+    // The marker is followed by the segment length and the precision and ID of the table.
+    let start = bytes.windows(2).position(|marker| marker == [0xff, 0xdb])? + 5;
+    bytes.get(start..start + 64)
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let size = UVec2::new(WIDTH, HEIGHT);
+    let texel = VALUES
+        .iter()
+        .flat_map(|value| value.to_le_bytes())
+        .collect::<Vec<_>>();
+    let values = export_sources.add(filled_image(
+        &mut images,
+        TextureFormat::Rgba32Float,
+        size,
+        &texel,
+    ));
+
+    for (dir, compression, precision, _, _) in EXR_SETTINGS {
+        commands.spawn((
+            ImageExport(values.clone()),
+            ImageExportSettings {
+                output_dir: output_dir(dir),
+                format: ExportFormat::Exr,
+                encoder: EncoderSettings {
+                    exr: ExrSettings {
+                        compression,
+                        precision,
+                        ..default()
+                    },
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+
+    let gray = export_sources.add(filled_image(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        size,
+        &[128, 128, 128, 255],
+    ));
+    for quality in JPEG_QUALITIES {
+        commands.spawn((
+            ImageExport(gray.clone()),
+            ImageExportSettings {
+                output_dir: output_dir(&format!("jpeg_{}", quality)),
+                format: ExportFormat::Jpeg,
+                encoder: EncoderSettings {
+                    jpeg: JpegSettings { quality },
+                    ..default()
+                },
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
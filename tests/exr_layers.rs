@@ -0,0 +1,120 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    EncoderSettings, ExportFormat, ExrSettings, ImageExport, ImageExportPass, ImageExportPasses,
+    ImageExportPlugin, ImageExportSettings, ImageExportSource,
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/exr_layers";
+
+#[test]
+fn test_exr_layers() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    let mut paths = std::fs::read_dir(OUTPUT_DIR)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    paths.sort();
+
+    if paths.is_empty() {
+        anyhow::bail!("expected exported frames");
+    }
+
+    // Every file holds the channels of the exporter and of its pass, and no pass is saved
+    // separately.
+    for path in paths {
+        if !path.is_file() {
+            anyhow::bail!("expected only files, got {}", path.display());
+        }
+
+        let image = exr::prelude::read_all_flat_layers_from_file(&path)?;
+        let mut channels = image
+            .layer_data
+            .iter()
+            .flat_map(|layer| &layer.channel_data.list)
+            .map(|channel| channel.name.to_string())
+            .collect::<Vec<_>>();
+        channels.sort();
+
+        let expected = ["A", "B", "G", "R", "aov.A", "aov.B", "aov.G", "aov.R"];
+        if channels != expected {
+            anyhow::bail!(
+                "expected channels {:?} in {}, got {:?}",
+                expected,
+                path.display(),
+                channels
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let mut image = || {
+        render_target(
+            &mut images,
+            TextureFormat::Rgba16Float,
+            UVec2::new(WIDTH, HEIGHT),
+        )
+    };
+    let color_image = image();
+    let aov_image = image();
+
+    for (image, order) in [(&color_image, 0), (&aov_image, 1)] {
+        commands.spawn((
+            Camera3d::default(),
+            Camera { order, ..default() },
+            RenderTarget::Image(image.clone().into()),
+        ));
+    }
+
+    commands.spawn((
+        ImageExport(export_sources.add(color_image)),
+        ImageExportSettings {
+            output_dir: OUTPUT_DIR.into(),
+            format: ExportFormat::Exr,
+            encoder: EncoderSettings {
+                exr: ExrSettings {
+                    combine_passes: true,
+                    ..default()
+                },
+                ..default()
+            },
+            ..default()
+        },
+        ImageExportPasses(vec![ImageExportPass::new(
+            "aov",
+            export_sources.add(aov_image),
+        )]),
+    ));
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,204 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ImageExport, ImageExportPlugin, ImageExportSettings, ImageExportSource, ImageExportStart,
+};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/file_names";
+
+/// The directories and templates of the exporters.
+const PADDED: (&str, &str) = ("padded", "{frame:06}.{ext}");
+const NAMED: (&str, &str) = ("named", "{name}/{frame}.{ext}");
+const ENTITY: (&str, &str) = ("entity", "{entity}-{timestamp}.{ext}");
+const OFFSET: (&str, &str) = ("offset", "{frame}.{ext}");
+const MALFORMED: [(&str, &str); 3] = [
+    ("unclosed", "{frame"),
+    ("unpadded", "{frame:6}"),
+    ("unknown", "{unknown}"),
+];
+
+const FRAME_OFFSET: u64 = 100;
+
+/// The entity of the exporter with the [`ENTITY`] template.
+#[derive(Resource, Clone, Default)]
+struct EntityExporter(Arc<Mutex<Option<Entity>>>);
+
+/// The templates of the exporters with malformed templates that have started.
+#[derive(Resource, Clone, Default)]
+struct StartedMalformed(Arc<Mutex<Vec<String>>>);
+
+#[test]
+fn test_file_names() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+    let entity_exporter = EntityExporter::default();
+    let started_malformed = StartedMalformed::default();
+
+    let start = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .insert_resource(entity_exporter.clone())
+        .insert_resource(started_malformed.clone())
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+    let end = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+    export_threads.finish();
+
+    // Frame numbers are padded to the given width.
+    let padded = file_names(&output_dir(PADDED.0))?;
+    if padded.first().map(String::as_str) != Some("000001.png")
+        || padded.iter().any(|name| name.len() != "000001.png".len())
+    {
+        anyhow::bail!("expected frame numbers with six digits, got {:?}", padded);
+    }
+
+    // Templates may include subdirectories.
+    let named = file_names(&format!("{}/exporter", output_dir(NAMED.0)))?;
+    if named.first().map(String::as_str) != Some("1.png") {
+        anyhow::bail!("expected frames in a subdirectory, got {:?}", named);
+    }
+
+    let entity = entity_exporter.0.lock().unwrap().unwrap();
+    let entity_names = file_names(&output_dir(ENTITY.0))?;
+    for name in &entity_names {
+        let timestamp = name
+            .strip_prefix(&format!("{}-", entity))
+            .and_then(|name| name.strip_suffix(".png"))
+            .and_then(|timestamp| timestamp.parse::<u64>().ok());
+        if timestamp.is_none_or(|timestamp| !(start..=end).contains(&timestamp)) {
+            anyhow::bail!(
+                "expected the entity {} and a timestamp, got {}",
+                entity,
+                name
+            );
+        }
+    }
+
+    // The offset is added to the frame numbers.
+    let offset = file_names(&output_dir(OFFSET.0))?;
+    if offset.first() != Some(&format!("{}.png", FRAME_OFFSET + 1)) {
+        anyhow::bail!(
+            "expected frame numbers from {}, got {:?}",
+            FRAME_OFFSET + 1,
+            offset
+        );
+    }
+
+    // Exporters with malformed templates are rejected before they start.
+    let started_malformed = started_malformed.0.lock().unwrap();
+    if !started_malformed.is_empty() {
+        anyhow::bail!(
+            "expected no malformed template to start, got {:?}",
+            started_malformed
+        );
+    }
+
+    for (dir, template) in MALFORMED {
+        if Path::new(&output_dir(dir)).exists() {
+            anyhow::bail!("expected no files for the template {:?}", template);
+        }
+    }
+
+    Ok(())
+}
+
+fn output_dir(dir: &str) -> String {
+    format!("{}/{}", OUTPUT_DIR, dir)
+}
+
+/// Returns the names of the files in `dir`, sorted by frame number.
+fn file_names(dir: &str) -> anyhow::Result<Vec<String>> {
+    let mut file_names = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            file_names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    file_names.sort_by_key(|name| (name.len(), name.clone()));
+
+    if file_names.is_empty() {
+        anyhow::bail!("expected files in {}", dir);
+    }
+
+    Ok(file_names)
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+    entity_exporter: Res<EntityExporter>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    let export_source = export_sources.add(output_texture_handle);
+    for (dir, template) in [PADDED, NAMED, ENTITY, OFFSET].into_iter().chain(MALFORMED) {
+        let entity = commands
+            .spawn((
+                ImageExport(export_source.clone()),
+                ImageExportSettings {
+                    output_dir: output_dir(dir),
+                    file_name: template.into(),
+                    frame_offset: if dir == OFFSET.0 { FRAME_OFFSET } else { 0 },
+                    ..default()
+                },
+                Name::new("exporter"),
+            ))
+            .id();
+
+        if dir == ENTITY.0 {
+            *entity_exporter.0.lock().unwrap() = Some(entity);
+        }
+    }
+}
+
+fn update(
+    exporters: Query<&ImageExportSettings, Added<ImageExportStart>>,
+    started_malformed: Res<StartedMalformed>,
+    mut app_exit_events: MessageWriter<AppExit>,
+    mut frame: Local<u32>,
+) {
+    for settings in &exporters {
+        if MALFORMED
+            .iter()
+            .any(|(_, template)| settings.file_name == *template)
+        {
+            started_malformed
+                .0
+                .lock()
+                .unwrap()
+                .push(settings.file_name.clone());
+        }
+    }
+
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,111 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ExportFormat, ImageExport, ImageExportPlugin, ImageExportSettings, ImageExportSource,
+};
+use image::ColorType;
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/formats";
+
+/// The formats to save an `Rgba16Float` texture in, with the pixel type their files should have.
+const FORMATS: [(ExportFormat, ColorType); 6] = [
+    (ExportFormat::Tiff, ColorType::Rgba32F),
+    (ExportFormat::Qoi, ColorType::Rgba8),
+    (ExportFormat::Bmp, ColorType::Rgba8),
+    (ExportFormat::Tga, ColorType::Rgba8),
+    (ExportFormat::Pnm, ColorType::Rgb8),
+    (ExportFormat::Hdr, ColorType::Rgb32F),
+];
+
+#[test]
+fn test_formats() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    for (format, color_type) in FORMATS {
+        let dir = output_dir(format);
+        let mut paths = std::fs::read_dir(&dir)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        paths.sort();
+
+        if paths.is_empty() {
+            anyhow::bail!("expected {:?} frames in {}", format, dir);
+        }
+
+        for path in paths {
+            if path.extension().and_then(|ext| ext.to_str()) != Some(format.extension()) {
+                anyhow::bail!("expected {:?} files, got {}", format, path.display());
+            }
+
+            let image = image::open(&path)
+                .map_err(|e| anyhow::anyhow!("failed to open {}: {}", path.display(), e))?;
+            if image.color() != color_type {
+                anyhow::bail!(
+                    "expected {:?} pixels in {}, got {:?}",
+                    color_type,
+                    path.display(),
+                    image.color()
+                );
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn output_dir(format: ExportFormat) -> String {
+    format!("{}/{}", OUTPUT_DIR, format.extension())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba16Float,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    let export_source = export_sources.add(output_texture_handle);
+    for (format, _) in FORMATS {
+        commands.spawn((
+            ImageExport(export_source.clone()),
+            ImageExportSettings {
+                output_dir: output_dir(format),
+                format,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use crate::common::test_app;
+use bevy::prelude::*;
+use bevy_image_export::ImageExportPlugin;
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+/// The frame rates of the apps. Frame rates below 4 fps exceed the default maximum delta of
+/// [`Time<Virtual>`].
+const FRAME_RATES: [f64; 2] = [30.0, 2.0];
+
+const FRAME_COUNT: usize = 10;
+
+#[derive(Resource, Clone, Default)]
+struct Deltas(Arc<Mutex<Vec<Duration>>>);
+
+#[test]
+fn test_frame_rate() -> anyhow::Result<()> {
+    for frame_rate in FRAME_RATES {
+        let deltas = Deltas::default();
+
+        test_app(WIDTH, HEIGHT)
+            .add_plugins(ImageExportPlugin::default().with_frame_rate(frame_rate))
+            .insert_resource(deltas.clone())
+            .add_systems(Update, update)
+            .run();
+
+        // The first update starts the clock, every later one advances it by exactly one frame.
+        let expected = Duration::from_secs_f64(1.0 / frame_rate);
+        let deltas = deltas.0.lock().unwrap();
+        if deltas[1..].iter().any(|delta| *delta != expected) {
+            anyhow::bail!(
+                "expected deltas of {:?} at {} fps, got {:?}",
+                expected,
+                frame_rate,
+                deltas
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+#[should_panic(expected = "positive and finite")]
+fn test_invalid_frame_rate() {
+    let _ = ImageExportPlugin::default().with_frame_rate(0.0);
+}
+
+fn update(time: Res<Time>, deltas: Res<Deltas>, mut app_exit_events: MessageWriter<AppExit>) {
+    let mut deltas = deltas.0.lock().unwrap();
+    deltas.push(time.delta());
+    if deltas.len() >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ExportedFrame, ImageExport, ImageExportMessages, ImageExportPlugin, ImageExportSource,
+};
+use std::sync::{Arc, Mutex};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 12;
+
+/// The exporter entity and the frames it sent.
+#[derive(Resource, Clone, Default)]
+struct Received(Arc<Mutex<(Option<Entity>, Vec<ExportedFrame>)>>);
+
+#[test]
+fn test_messages() -> anyhow::Result<()> {
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+    let received = Received::default();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .insert_resource(received.clone())
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    let (exporter, frames) = &*received.0.lock().unwrap();
+    if frames.is_empty() {
+        anyhow::bail!("expected frame messages");
+    }
+
+    let frame_len = (WIDTH * HEIGHT * 4) as usize;
+    for (index, frame) in frames.iter().enumerate() {
+        if Some(frame.entity) != *exporter {
+            anyhow::bail!("expected frames of {:?}, got {}", exporter, frame.entity);
+        }
+
+        if frame.frame_id != index as u64 + 1 {
+            anyhow::bail!("expected frame {}, got frame {}", index + 1, frame.frame_id);
+        }
+
+        if (frame.width, frame.height) != (WIDTH, HEIGHT) || frame.image_bytes.len() != frame_len {
+            anyhow::bail!(
+                "expected {}x{} frames of {} bytes, got {}x{} with {} bytes",
+                WIDTH,
+                HEIGHT,
+                frame_len,
+                frame.width,
+                frame.height,
+                frame.image_bytes.len()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+    received: Res<Received>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    let exporter = commands
+        .spawn((
+            ImageExport(export_sources.add(output_texture_handle)),
+            ImageExportMessages,
+        ))
+        .id();
+    received.0.lock().unwrap().0 = Some(exporter);
+}
+
+fn update(
+    mut app_exit_events: MessageWriter<AppExit>,
+    mut frame_messages: MessageReader<ExportedFrame>,
+    received: Res<Received>,
+    mut frame: Local<u32>,
+) {
+    received
+        .0
+        .lock()
+        .unwrap()
+        .1
+        .extend(frame_messages.read().cloned());
+
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{ImageExport, ImageExportPlugin, ImageExportSettings, ImageExportSource};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const PREVIEW_SIZE: u32 = 8;
+
+const FRAME_COUNT: u32 = 10;
+
+const FULL_DIR: &str = "out/outputs/full";
+const PREVIEW_DIR: &str = "out/outputs/preview";
+
+#[test]
+fn test_shared_source() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(FULL_DIR);
+    let _ = std::fs::remove_dir_all(PREVIEW_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    // Both exporters receive the same frames, but only the preview is scaled down.
+    let full_sizes = image_sizes(FULL_DIR)?;
+    let preview_sizes = image_sizes(PREVIEW_DIR)?;
+    if full_sizes.is_empty() || full_sizes.len() != preview_sizes.len() {
+        anyhow::bail!(
+            "expected the same frames for both exporters, got {} and {}",
+            full_sizes.len(),
+            preview_sizes.len()
+        );
+    }
+
+    if full_sizes.iter().any(|&size| size != (WIDTH, HEIGHT)) {
+        anyhow::bail!("expected full size frames, got {:?}", full_sizes);
+    }
+
+    if preview_sizes
+        .iter()
+        .any(|&size| size != (PREVIEW_SIZE, PREVIEW_SIZE))
+    {
+        anyhow::bail!("expected preview frames, got {:?}", preview_sizes);
+    }
+
+    Ok(())
+}
+
+/// Returns the sizes of the images in `dir`, in frame order.
+fn image_sizes(dir: &str) -> anyhow::Result<Vec<(u32, u32)>> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            image::image_dimensions(path)
+                .map_err(|e| anyhow::anyhow!("failed to open {}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    let export_source = export_sources.add(output_texture_handle);
+    commands.spawn((
+        ImageExport(export_source.clone()),
+        ImageExportSettings {
+            output_dir: FULL_DIR.into(),
+            ..default()
+        },
+    ));
+    commands.spawn((
+        ImageExport(export_source),
+        ImageExportSettings {
+            output_dir: PREVIEW_DIR.into(),
+            max_size: Some(UVec2::splat(PREVIEW_SIZE)),
+            ..default()
+        },
+    ));
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
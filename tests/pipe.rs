@@ -0,0 +1,105 @@
+#![cfg(unix)]
+
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ImageExport, ImageExportPipe, ImageExportPlugin, ImageExportSource, ImageExportState,
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const IMAGE_COUNT: u32 = 16;
+
+/// The updates during which the export is stopped, which closes the first process.
+const STOPPED_FRAMES: std::ops::Range<u32> = 8..10;
+
+const OUTPUT_DIR: &str = "out/pipe";
+
+#[test]
+fn test_pipe() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    // Only returns once both processes have received the end of their input.
+    export_threads.finish();
+
+    // Each process writes its own file, named after its process id.
+    let outputs = std::fs::read_dir(OUTPUT_DIR)?
+        .map(|entry| Ok(std::fs::read(entry?.path())?))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    if outputs.len() != 2 {
+        anyhow::bail!("expected a process per recording, got {}", outputs.len());
+    }
+
+    // The exact number of frames depends on when Bevy starts rendering, but the streams must only
+    // contain complete frames.
+    let frame_len = (WIDTH * HEIGHT * 4) as usize;
+    for output in outputs {
+        if output.is_empty() || output.len() % frame_len != 0 {
+            anyhow::bail!(
+                "expected whole frames of {} bytes, got {} bytes",
+                frame_len,
+                output.len()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    commands.spawn((
+        ImageExport(export_sources.add(output_texture_handle)),
+        ImageExportPipe::new("sh").args([
+            "-c".to_string(),
+            format!("mkdir -p {0} && cat > {0}/$$.raw", OUTPUT_DIR),
+        ]),
+    ));
+}
+
+fn update(
+    mut app_exit_events: MessageWriter<AppExit>,
+    mut states: Query<&mut ImageExportState>,
+    mut frame: Local<u32>,
+) {
+    *frame += 1;
+    let frame = *frame;
+
+    for mut state in &mut states {
+        *state = if STOPPED_FRAMES.contains(&frame) {
+            ImageExportState::Stopped
+        } else {
+            ImageExportState::Recording
+        };
+    }
+
+    if frame >= IMAGE_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,124 @@
+mod common;
+
+use crate::common::test_app;
+use bevy::{
+    camera::RenderTarget,
+    core_pipeline::prepass::{DepthPrepass, NormalPrepass},
+    prelude::*,
+    render::render_resource::TextureFormat,
+};
+use bevy_image_export::{
+    ImageExport, ImageExportPass, ImageExportPasses, ImageExportPlugin, ImageExportPrepass,
+    ImageExportSettings, ImageExportSource,
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/prepass";
+
+#[test]
+fn test_prepass() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    // Every pass has a file for every frame of the exporter.
+    let frames = file_names(OUTPUT_DIR)?;
+    if frames.is_empty() {
+        anyhow::bail!("expected exported frames");
+    }
+
+    for pass in ["depth", "normal"] {
+        let pass_frames = file_names(&format!("{}/{}", OUTPUT_DIR, pass))?;
+        if pass_frames != frames {
+            anyhow::bail!(
+                "expected the {} pass to match the frames {:?}, got {:?}",
+                pass,
+                frames,
+                pass_frames
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Returns the names of the files in `dir`, without its subdirectories.
+fn file_names(dir: &str) -> anyhow::Result<Vec<String>> {
+    let mut file_names = Vec::new();
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        if entry.file_type()?.is_file() {
+            file_names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+    }
+    file_names.sort();
+
+    Ok(file_names)
+}
+
+fn setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let size = UVec2::new(WIDTH, HEIGHT);
+    let color_image = images.add(ImageExportPrepass::image(
+        size,
+        TextureFormat::Rgba8UnormSrgb,
+    ));
+    let depth_image = images.add(ImageExportPrepass::image(size, TextureFormat::R32Float));
+    let normal_image = images.add(ImageExportPrepass::image(size, TextureFormat::Rgba8Unorm));
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(color_image.clone().into()),
+        Msaa::Off,
+        DepthPrepass,
+        NormalPrepass,
+        ImageExportPrepass {
+            depth: Some(depth_image.clone()),
+            normal: Some(normal_image.clone()),
+            ..default()
+        },
+        Transform::from_translation(4.2 * Vec3::Z),
+    ));
+
+    commands.spawn((
+        ImageExport(export_sources.add(color_image)),
+        ImageExportSettings {
+            output_dir: OUTPUT_DIR.into(),
+            ..default()
+        },
+        ImageExportPasses(vec![
+            ImageExportPass::new("depth", export_sources.add(depth_image)),
+            ImageExportPass::new("normal", export_sources.add(normal_image)),
+        ]),
+    ));
+
+    commands.spawn((
+        Mesh3d(meshes.add(Mesh::from(Cuboid::default()))),
+        MeshMaterial3d(materials.add(Color::srgb(1.0, 0.0, 0.0))),
+    ));
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,93 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ImageExport, ImageExportFinished, ImageExportPlugin, ImageExportRange, ImageExportSettings,
+    ImageExportSource,
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+/// Gives up if the exporter hasn't finished after this many frames.
+const MAX_FRAMES: u32 = 60;
+
+const OUTPUT_DIR: &str = "out/range";
+
+#[test]
+fn test_range() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    let mut app = test_app(WIDTH, HEIGHT);
+    app.add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update);
+
+    if app.run() != AppExit::Success {
+        anyhow::bail!("the exporter didn't finish after {} frames", MAX_FRAMES);
+    }
+
+    export_threads.finish();
+
+    let mut frames = std::fs::read_dir(OUTPUT_DIR)?
+        .map(|entry| -> anyhow::Result<u32> {
+            let path = entry?.path();
+            let stem = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("");
+            Ok(stem.parse()?)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    frames.sort();
+
+    if frames != [2, 5, 8] {
+        anyhow::bail!("expected frames [2, 5, 8], got {:?}", frames);
+    }
+
+    Ok(())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    commands.spawn((
+        ImageExport(export_sources.add(output_texture_handle)),
+        ImageExportSettings {
+            output_dir: OUTPUT_DIR.into(),
+            ..default()
+        },
+        ImageExportRange::new(2..=8).stride(3),
+    ));
+}
+
+fn update(
+    mut app_exit_events: MessageWriter<AppExit>,
+    mut finished: MessageReader<ImageExportFinished>,
+    mut frame: Local<u32>,
+) {
+    *frame += 1;
+
+    if finished.read().next().is_some() {
+        app_exit_events.write(AppExit::Success);
+    } else if *frame >= MAX_FRAMES {
+        app_exit_events.write(AppExit::error());
+    }
+}
@@ -0,0 +1,94 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{ImageExport, ImageExportPlugin, ImageExportSettings, ImageExportSource};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+/// The exported region, from its top left to its bottom right corner.
+const REGION: ([u32; 2], [u32; 2]) = ([4, 2], [12, 6]);
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/region";
+
+#[test]
+fn test_region() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    let (min, max) = REGION;
+    let region_size = (max[0] - min[0], max[1] - min[1]);
+    let sizes = image_sizes(OUTPUT_DIR)?;
+    if sizes.is_empty() || sizes.iter().any(|&size| size != region_size) {
+        anyhow::bail!("expected frames of size {:?}, got {:?}", region_size, sizes);
+    }
+
+    Ok(())
+}
+
+/// Returns the sizes of the images in `dir`, in frame order.
+fn image_sizes(dir: &str) -> anyhow::Result<Vec<(u32, u32)>> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            image::image_dimensions(path)
+                .map_err(|e| anyhow::anyhow!("failed to open {}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    let (min, max) = REGION;
+    commands.spawn((
+        ImageExport(
+            export_sources.add(
+                ImageExportSource::new(output_texture_handle)
+                    .with_region(URect::from_corners(min.into(), max.into())),
+            ),
+        ),
+        ImageExportSettings {
+            output_dir: OUTPUT_DIR.into(),
+            ..default()
+        },
+    ));
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
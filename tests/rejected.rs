@@ -0,0 +1,156 @@
+mod common;
+
+use crate::common::{filled_image, render_target, test_app};
+use bevy::{
+    asset::RenderAssetUsages, camera::RenderTarget, prelude::*,
+    render::render_resource::TextureFormat,
+};
+use bevy_image_export::{
+    ExportFormat, ImageExport, ImageExportPass, ImageExportPasses, ImageExportPlugin,
+    ImageExportSettings, ImageExportSource, StartBehavior,
+};
+use std::path::Path;
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/rejected";
+
+const FORMATS: [ExportFormat; 9] = [
+    ExportFormat::Png,
+    ExportFormat::Jpeg,
+    ExportFormat::Exr,
+    ExportFormat::Tiff,
+    ExportFormat::Qoi,
+    ExportFormat::Bmp,
+    ExportFormat::Tga,
+    ExportFormat::Pnm,
+    ExportFormat::Hdr,
+];
+
+#[test]
+fn test_rejected() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    // No camera renders to the image with the unsupported format.
+    let export_plugin =
+        ImageExportPlugin::default().with_start_behavior(StartBehavior::WaitForSource);
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    // The exporter with a supported format and texture proves that frames were exported at all.
+    if std::fs::read_dir(output_dir("supported"))?.count() == 0 {
+        anyhow::bail!("expected frames of the supported exporter");
+    }
+
+    // Formats are checked against the render world, so images that only exist there are exported.
+    if std::fs::read_dir(output_dir("render_world"))?.count() == 0 {
+        anyhow::bail!("expected frames of an image that only exists in the render world");
+    }
+
+    // Exporters with formats whose cargo feature is disabled never start.
+    for format in FORMATS.into_iter().filter(|format| !format.is_enabled()) {
+        if Path::new(&output_dir(format.extension())).exists() {
+            anyhow::bail!("expected no files in the disabled format {:?}", format);
+        }
+    }
+
+    // Neither do exporters with a source, or pass, whose texture format can't be decoded.
+    for dir in ["unsupported", "unsupported_pass"] {
+        if Path::new(&output_dir(dir)).exists() {
+            anyhow::bail!(
+                "expected no files in {} for the unsupported texture format",
+                dir
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn output_dir(dir: &str) -> String {
+    format!("{}/{}", OUTPUT_DIR, dir)
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let size = UVec2::new(WIDTH, HEIGHT);
+    let output_texture_handle = render_target(&mut images, TextureFormat::Rgba8UnormSrgb, size);
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    let export_source = export_sources.add(output_texture_handle);
+    commands.spawn((
+        ImageExport(export_source.clone()),
+        ImageExportSettings {
+            output_dir: output_dir("supported"),
+            ..default()
+        },
+    ));
+
+    let render_world = filled_image(&mut images, TextureFormat::Rgba8Unorm, size, &[0; 4]);
+    images.get_mut(&render_world).unwrap().asset_usage = RenderAssetUsages::RENDER_WORLD;
+    commands.spawn((
+        ImageExport(export_sources.add(render_world)),
+        ImageExportSettings {
+            output_dir: output_dir("render_world"),
+            ..default()
+        },
+    ));
+
+    for format in FORMATS.into_iter().filter(|format| !format.is_enabled()) {
+        commands.spawn((
+            ImageExport(export_source.clone()),
+            ImageExportSettings {
+                output_dir: output_dir(format.extension()),
+                format,
+                ..default()
+            },
+        ));
+    }
+
+    let unsupported = export_sources.add(filled_image(
+        &mut images,
+        TextureFormat::Rg8Unorm,
+        size,
+        &[0, 0],
+    ));
+    commands.spawn((
+        ImageExport(unsupported.clone()),
+        ImageExportSettings {
+            output_dir: output_dir("unsupported"),
+            ..default()
+        },
+    ));
+
+    commands.spawn((
+        ImageExport(export_source),
+        ImageExportSettings {
+            output_dir: output_dir("unsupported_pass"),
+            ..default()
+        },
+        ImageExportPasses(vec![ImageExportPass::new("rg8", unsupported)]),
+    ));
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,133 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{
+    camera::RenderTarget,
+    prelude::*,
+    render::render_resource::{Extent3d, TextureFormat},
+};
+use bevy_image_export::{
+    ImageExport, ImageExportPlugin, ImageExportSettings, ImageExportSource, ImageExportStart,
+    ResizeBehavior,
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+/// The frame from which on the source image has twice its size.
+const RESIZE_FRAME: u64 = 4;
+
+const FRAME_COUNT: u64 = 8;
+
+const FOLLOW_DIR: &str = "out/resize/follow";
+const REJECT_DIR: &str = "out/resize/reject";
+
+#[derive(Resource)]
+struct OutputTexture(Handle<Image>);
+
+#[test]
+fn test_resize() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(FOLLOW_DIR);
+    let _ = std::fs::remove_dir_all(REJECT_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    let follow_sizes = image_sizes(FOLLOW_DIR)?;
+    if !follow_sizes.contains(&(2 * WIDTH, 2 * HEIGHT)) {
+        anyhow::bail!("expected resized frames, got {:?}", follow_sizes);
+    }
+
+    let reject_sizes = image_sizes(REJECT_DIR)?;
+    if reject_sizes.is_empty() || reject_sizes.iter().any(|&size| size != (WIDTH, HEIGHT)) {
+        anyhow::bail!(
+            "expected only frames of the original size, got {:?}",
+            reject_sizes
+        );
+    }
+
+    Ok(())
+}
+
+/// Returns the sizes of the images in `dir`, in frame order.
+fn image_sizes(dir: &str) -> anyhow::Result<Vec<(u32, u32)>> {
+    let mut paths = std::fs::read_dir(dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    paths.sort();
+
+    paths
+        .iter()
+        .map(|path| {
+            image::image_dimensions(path)
+                .map_err(|e| anyhow::anyhow!("failed to open {}: {}", path.display(), e))
+        })
+        .collect()
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    let export_source = export_sources.add(output_texture_handle.clone());
+    for (output_dir, on_resize) in [
+        (FOLLOW_DIR, ResizeBehavior::Follow),
+        (REJECT_DIR, ResizeBehavior::Reject),
+    ] {
+        commands.spawn((
+            ImageExport(export_source.clone()),
+            ImageExportSettings {
+                output_dir: output_dir.into(),
+                on_resize,
+                ..default()
+            },
+        ));
+    }
+
+    commands.insert_resource(OutputTexture(output_texture_handle));
+}
+
+fn update(
+    mut app_exit_events: MessageWriter<AppExit>,
+    exporters: Query<&ImageExportStart>,
+    output_texture: Res<OutputTexture>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let Some(frame) = exporters.iter().map(ImageExportStart::frame_id).max() else {
+        return;
+    };
+
+    if frame == RESIZE_FRAME {
+        if let Some(image) = images.get_mut(&output_texture.0) {
+            image.resize(Extent3d {
+                width: 2 * WIDTH,
+                height: 2 * HEIGHT,
+                ..default()
+            });
+        }
+    }
+
+    if frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ExportSink, ExportThreads, ExportedFrame, ImageExport, ImageExportPlugin, ImageExportSink,
+    ImageExportSource,
+};
+use std::sync::{Arc, Mutex};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+/// Records the numbers of the frames it receives.
+#[derive(Clone, Default)]
+struct CountingSink(Arc<Mutex<Vec<u64>>>);
+
+impl ExportSink for CountingSink {
+    fn write_frame(&self, frame: ExportedFrame, _export_threads: &ExportThreads) {
+        self.0.lock().unwrap().push(frame.frame_id);
+    }
+}
+
+#[derive(Resource, Clone)]
+struct Sink(CountingSink);
+
+#[test]
+fn test_sink() -> anyhow::Result<()> {
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+    let sink = CountingSink::default();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .insert_resource(Sink(sink.clone()))
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    // Every frame reaches the sink exactly once, in order.
+    let frames = sink.0.lock().unwrap();
+    let expected = (1..=frames.len() as u64).collect::<Vec<_>>();
+    if frames.is_empty() || *frames != expected {
+        anyhow::bail!("expected frames in order, got {:?}", frames);
+    }
+
+    Ok(())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+    sink: Res<Sink>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    commands.spawn((
+        ImageExport(export_sources.add(output_texture_handle)),
+        ImageExportSink::new(sink.0.clone()),
+    ));
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
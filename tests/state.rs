@@ -0,0 +1,108 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{camera::RenderTarget, prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ImageExport, ImageExportPlugin, ImageExportSettings, ImageExportSource, ImageExportState,
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+/// The updates during which the export is paused.
+const PAUSED_FRAMES: std::ops::Range<u32> = 4..8;
+
+const FRAME_COUNT: u32 = 12;
+
+const OUTPUT_DIR: &str = "out/state";
+
+#[test]
+fn test_pause_resume() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    // Pausing skips frames, but the numbering continues where it stopped.
+    let mut frames = std::fs::read_dir(OUTPUT_DIR)?
+        .map(|entry| -> anyhow::Result<u32> {
+            let path = entry?.path();
+            let stem = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .unwrap_or("");
+            Ok(stem.parse()?)
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    frames.sort();
+
+    let expected = (1..=frames.len() as u32).collect::<Vec<_>>();
+    if frames.is_empty() || frames != expected {
+        anyhow::bail!("expected contiguous frame numbers, got {:?}", frames);
+    }
+
+    let max_frames = (FRAME_COUNT - PAUSED_FRAMES.len() as u32) as usize;
+    if frames.len() > max_frames {
+        anyhow::bail!(
+            "expected at most {} frames, got {}",
+            max_frames,
+            frames.len()
+        );
+    }
+
+    Ok(())
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+    ));
+
+    commands.spawn((
+        ImageExport(export_sources.add(output_texture_handle)),
+        ImageExportSettings {
+            output_dir: OUTPUT_DIR.into(),
+            ..default()
+        },
+    ));
+}
+
+fn update(
+    mut app_exit_events: MessageWriter<AppExit>,
+    mut states: Query<&mut ImageExportState>,
+    mut frame: Local<u32>,
+) {
+    *frame += 1;
+    let frame = *frame;
+
+    for mut state in &mut states {
+        *state = if PAUSED_FRAMES.contains(&frame) {
+            ImageExportState::Paused
+        } else {
+            ImageExportState::Recording
+        };
+    }
+
+    if frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
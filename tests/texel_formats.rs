@@ -0,0 +1,138 @@
+mod common;
+
+use crate::common::{filled_image, test_app};
+use bevy::{prelude::*, render::render_resource::TextureFormat};
+use bevy_image_export::{
+    ExportFormat, ImageExport, ImageExportPlugin, ImageExportSettings, ImageExportSource,
+    StartBehavior,
+};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/texel_formats";
+
+/// Blue 10, green 20, red 30 and alpha 40.
+const BGRA8_TEXEL: [u8; 4] = [10, 20, 30, 40];
+
+/// Red 1023, green 512, blue 0 and alpha 3, packed from the lowest bits.
+const RGB10A2_TEXEL: u32 = 1023 | (512 << 10) | (3 << 30);
+
+/// Red 1.0, green 0.5 and blue 2.0, packed from the lowest bits. Red and green are 11-bit floats
+/// with 6 mantissa bits, blue is a 10-bit float with 5 mantissa bits, all with an exponent bias of
+/// 15.
+const RG11B10_TEXEL: u32 = (15 << 6) | ((14 << 6) << 11) | ((16 << 5) << 22);
+
+#[test]
+fn test_texel_formats() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+
+    // No camera renders to the images.
+    let export_plugin =
+        ImageExportPlugin::default().with_start_behavior(StartBehavior::WaitForSource);
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    // Channels are swapped to RGBA.
+    let bgra8 = image::open(first_file("bgra8")?)?;
+    let pixel = bgra8
+        .as_rgba8()
+        .ok_or_else(|| anyhow::anyhow!("expected 8-bit RGBA, got {:?}", bgra8.color()))?
+        .get_pixel(0, 0)
+        .0;
+    if pixel != [30, 20, 10, 40] {
+        anyhow::bail!("unexpected Bgra8Unorm pixel {:?}", pixel);
+    }
+
+    // 10-bit and 2-bit channels are expanded to the full 16-bit range.
+    let rgb10a2 = image::open(first_file("rgb10a2")?)?;
+    let pixel = rgb10a2
+        .as_rgba16()
+        .ok_or_else(|| anyhow::anyhow!("expected 16-bit RGBA, got {:?}", rgb10a2.color()))?
+        .get_pixel(0, 0)
+        .0;
+    if pixel != [65535, 512 * 65535 / 1023, 0, 65535] {
+        anyhow::bail!("unexpected Rgb10a2Unorm pixel {:?}", pixel);
+    }
+
+    // Packed floats are unpacked as they are, with an opaque alpha.
+    let rg11b10 = image::open(first_file("rg11b10")?)?;
+    let pixel = rg11b10
+        .as_rgba32f()
+        .ok_or_else(|| anyhow::anyhow!("expected float RGBA, got {:?}", rg11b10.color()))?
+        .get_pixel(0, 0)
+        .0;
+    if pixel != [1.0, 0.5, 2.0, 1.0] {
+        anyhow::bail!("unexpected Rg11b10Ufloat pixel {:?}", pixel);
+    }
+
+    Ok(())
+}
+
+/// Returns the path of the first file saved by the given exporter.
+fn first_file(exporter: &str) -> anyhow::Result<std::path::PathBuf> {
+    let dir = format!("{}/{}", OUTPUT_DIR, exporter);
+    let mut paths = std::fs::read_dir(&dir)?
+        .map(|entry| Ok(entry?.path()))
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    paths.sort();
+
+    paths
+        .into_iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("expected frames in {}", dir))
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let size = UVec2::new(WIDTH, HEIGHT);
+
+    // The images have known texels, and are saved in formats that keep their precision.
+    let bgra8 = filled_image(&mut images, TextureFormat::Bgra8Unorm, size, &BGRA8_TEXEL);
+    let rgb10a2 = filled_image(
+        &mut images,
+        TextureFormat::Rgb10a2Unorm,
+        size,
+        &RGB10A2_TEXEL.to_le_bytes(),
+    );
+    let rg11b10 = filled_image(
+        &mut images,
+        TextureFormat::Rg11b10Ufloat,
+        size,
+        &RG11B10_TEXEL.to_le_bytes(),
+    );
+
+    for (name, image, format) in [
+        ("bgra8", bgra8, ExportFormat::Png),
+        ("rgb10a2", rgb10a2, ExportFormat::Tiff),
+        ("rg11b10", rg11b10, ExportFormat::Exr),
+    ] {
+        commands.spawn((
+            ImageExport(export_sources.add(image)),
+            ImageExportSettings {
+                output_dir: format!("{}/{}", OUTPUT_DIR, name),
+                format,
+                ..default()
+            },
+        ));
+    }
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}
@@ -0,0 +1,71 @@
+use bevy_image_export::{ExportThreadSettings, ExportThreads, QueueFullBehavior};
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    mpsc, Arc,
+};
+
+#[test]
+fn test_queue_full() -> anyhow::Result<()> {
+    for on_full in [QueueFullBehavior::Block, QueueFullBehavior::DropFrame] {
+        let export_threads = ExportThreads::new(ExportThreadSettings {
+            worker_count: 1,
+            queue_capacity: 1,
+            on_full,
+        });
+        let done = Arc::new(AtomicUsize::new(0));
+
+        // Occupies the only worker until the other jobs have been queued.
+        let (started_sender, started) = mpsc::channel();
+        let (release, released) = mpsc::channel::<()>();
+        export_threads.execute({
+            let done = done.clone();
+            move || {
+                started_sender.send(()).unwrap();
+                released.recv().unwrap();
+                done.fetch_add(1, Ordering::SeqCst);
+            }
+        });
+        started.recv()?;
+
+        // The first job fills the queue.
+        let job = || {
+            let done = done.clone();
+            move || {
+                done.fetch_add(1, Ordering::SeqCst);
+            }
+        };
+        if !export_threads.execute(job()) {
+            anyhow::bail!("{:?}: expected the queue to take a job", on_full);
+        }
+
+        let expected = match on_full {
+            QueueFullBehavior::Block => {
+                // Blocks until the worker takes the next job off the queue.
+                std::thread::spawn(move || {
+                    std::thread::sleep(std::time::Duration::from_millis(100));
+                    release.send(()).unwrap();
+                });
+                if !export_threads.execute(job()) {
+                    anyhow::bail!("{:?}: expected the job to wait for the queue", on_full);
+                }
+                3
+            }
+            QueueFullBehavior::DropFrame => {
+                if export_threads.execute(job()) {
+                    anyhow::bail!("{:?}: expected the job to be dropped", on_full);
+                }
+                release.send(())?;
+                2
+            }
+        };
+
+        export_threads.finish();
+
+        let done = done.load(Ordering::SeqCst);
+        if done != expected {
+            anyhow::bail!("{:?}: expected {} jobs, got {}", on_full, expected, done);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,252 @@
+mod common;
+
+use crate::common::{render_target, test_app};
+use bevy::{
+    camera::RenderTarget, core_pipeline::tonemapping::Tonemapping, prelude::*,
+    render::render_resource::TextureFormat,
+};
+use bevy_image_export::{
+    BitDepth, EncoderSettings, ExportFormat, ImageExport, ImageExportPlugin, ImageExportSettings,
+    ImageExportSource, TransferFunction,
+};
+use std::{fs::File, io::BufReader, path::Path};
+
+const WIDTH: u32 = 16;
+const HEIGHT: u32 = 16;
+
+const FRAME_COUNT: u32 = 10;
+
+const OUTPUT_DIR: &str = "out/transfer_function";
+
+/// The exporter of an `Rgba8UnormSrgb` texture, saved as linear 16-bit PNG files.
+const SRGB8_OUTPUT: &str = "srgb8";
+
+/// The linear value that the texture is cleared to.
+const CLEAR_VALUE: f32 = 0.18;
+
+/// An exporter of the `Rgba16Float` texture, with the value and the H.273 transfer characteristics
+/// that its files should have.
+struct Output {
+    name: &'static str,
+    format: ExportFormat,
+    transfer_function: Option<TransferFunction>,
+    value: f32,
+    cicp: Option<u8>,
+}
+
+const OUTPUTS: [Output; 3] = [
+    Output {
+        name: "linear",
+        format: ExportFormat::Png,
+        transfer_function: Some(TransferFunction::Linear),
+        value: CLEAR_VALUE,
+        cicp: Some(8),
+    },
+    Output {
+        name: "pq",
+        format: ExportFormat::Png,
+        transfer_function: Some(TransferFunction::Pq),
+        // The PQ code of 0.18 times the reference white of 203 cd/m².
+        value: 0.4109,
+        cicp: Some(16),
+    },
+    Output {
+        name: "exr",
+        format: ExportFormat::Exr,
+        transfer_function: None,
+        value: CLEAR_VALUE,
+        cicp: None,
+    },
+];
+
+#[test]
+fn test_transfer_function() -> anyhow::Result<()> {
+    let _ = std::fs::remove_dir_all(OUTPUT_DIR);
+    let export_plugin = ImageExportPlugin::default();
+    let export_threads = export_plugin.threads.clone();
+
+    test_app(WIDTH, HEIGHT)
+        .add_plugins(export_plugin)
+        .add_systems(Startup, setup)
+        .add_systems(Update, update)
+        .run();
+
+    export_threads.finish();
+
+    for output in OUTPUTS {
+        let dir = output_dir(&output);
+        let mut paths = std::fs::read_dir(&dir)?
+            .map(|entry| Ok(entry?.path()))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        paths.sort();
+
+        if paths.is_empty() {
+            anyhow::bail!("expected frames in {}", dir);
+        }
+
+        for path in paths {
+            let image = image::open(&path)
+                .map_err(|e| anyhow::anyhow!("failed to open {}: {}", path.display(), e))?;
+            let red = image.to_rgba32f().get_pixel(0, 0)[0];
+            if (red - output.value).abs() > 0.01 {
+                anyhow::bail!(
+                    "expected {} in {}, got {}",
+                    output.value,
+                    path.display(),
+                    red
+                );
+            }
+
+            match output.format {
+                ExportFormat::Png => {
+                    let cicp = png_cicp(&path)?;
+                    if cicp != output.cicp {
+                        anyhow::bail!(
+                            "expected transfer characteristics {:?} in {}, got {:?}",
+                            output.cicp,
+                            path.display(),
+                            cicp
+                        );
+                    }
+                }
+                ExportFormat::Exr => {
+                    let meta = exr::meta::MetaData::read_from_file(&path, false)?;
+                    if meta.headers[0].shared_attributes.chromaticities.is_none() {
+                        anyhow::bail!("expected chromaticities in {}", path.display());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    // Values of 8-bit textures are re-encoded with 16 bits, rather than quantized to 8 bits first.
+    let dir = format!("{}/{}", OUTPUT_DIR, SRGB8_OUTPUT);
+    for entry in std::fs::read_dir(&dir)? {
+        let path = entry?.path();
+        let image = image::open(&path)?;
+        let red = image
+            .as_rgba16()
+            .ok_or_else(|| anyhow::anyhow!("expected 16-bit RGBA, got {:?}", image.color()))?
+            .get_pixel(0, 0)[0];
+        let exact = (0..=255u8).any(|code| {
+            let linear = srgb_to_linear(code as f32 / 255.0) * 65535.0;
+            (linear - red as f32).abs() <= 1.0
+        });
+        if !exact {
+            anyhow::bail!(
+                "expected the linear value of an 8-bit sRGB code in {}, got {}",
+                path.display(),
+                red
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// Returns the transfer characteristics of the `cICP` chunk of a PNG file.
+fn png_cicp(path: &Path) -> anyhow::Result<Option<u8>> {
+    let reader = png::Decoder::new(BufReader::new(File::open(path)?)).read_info()?;
+    Ok(reader
+        .info()
+        .coding_independent_code_points
+        .map(|cicp| cicp.transfer_function))
+}
+
+fn output_dir(output: &Output) -> String {
+    format!("{}/{}", OUTPUT_DIR, output.name)
+}
+
+fn setup(
+    mut commands: Commands,
+    mut images: ResMut<Assets<Image>>,
+    mut export_sources: ResMut<Assets<ImageExportSource>>,
+) {
+    let output_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba16Float,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(output_texture_handle.clone().into()),
+        Camera {
+            clear_color: ClearColorConfig::Custom(Color::linear_rgb(
+                CLEAR_VALUE,
+                CLEAR_VALUE,
+                CLEAR_VALUE,
+            )),
+            ..default()
+        },
+        Tonemapping::None,
+    ));
+
+    let export_source = export_sources.add(output_texture_handle);
+    for output in OUTPUTS {
+        // 16 bits keep dark linear values precise.
+        let mut encoder = EncoderSettings::default();
+        encoder.png.bit_depth = BitDepth::Sixteen;
+
+        commands.spawn((
+            ImageExport(export_source.clone()),
+            ImageExportSettings {
+                output_dir: output_dir(&output),
+                format: output.format,
+                encoder,
+                transfer_function: output.transfer_function,
+                ..default()
+            },
+        ));
+    }
+
+    let srgb8_texture_handle = render_target(
+        &mut images,
+        TextureFormat::Rgba8UnormSrgb,
+        UVec2::new(WIDTH, HEIGHT),
+    );
+
+    commands.spawn((
+        Camera3d::default(),
+        RenderTarget::Image(srgb8_texture_handle.clone().into()),
+        Camera {
+            clear_color: ClearColorConfig::Custom(Color::linear_rgb(
+                CLEAR_VALUE,
+                CLEAR_VALUE,
+                CLEAR_VALUE,
+            )),
+            order: 1,
+            ..default()
+        },
+        Tonemapping::None,
+    ));
+
+    let mut encoder = EncoderSettings::default();
+    encoder.png.bit_depth = BitDepth::Sixteen;
+    commands.spawn((
+        ImageExport(export_sources.add(srgb8_texture_handle)),
+        ImageExportSettings {
+            output_dir: format!("{}/{}", OUTPUT_DIR, SRGB8_OUTPUT),
+            format: ExportFormat::Png,
+            encoder,
+            transfer_function: Some(TransferFunction::Linear),
+            ..default()
+        },
+    ));
+}
+
+fn update(mut app_exit_events: MessageWriter<AppExit>, mut frame: Local<u32>) {
+    *frame += 1;
+    if *frame >= FRAME_COUNT {
+        app_exit_events.write(AppExit::Success);
+    }
+}